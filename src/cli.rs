@@ -1,10 +1,20 @@
-use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
 use params_parser::parse_key_val;
 
 #[derive(Debug, Parser)]
 pub struct Cli {
 	#[clap(subcommand)]
 	pub commands: Commands,
+
+	/// Config file to read instead of discovering them
+	#[arg(long, global = true)]
+	pub config: Option<PathBuf>,
+
+	/// Profile of the config files to select, like `work` for `[profiles.work]`
+	#[arg(long, global = true)]
+	pub profile: Option<String>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -15,22 +25,127 @@ pub enum Commands {
 	/// Generates a file code from a given template
 	#[clap(alias = "g")]
 	Generate(GenerateCommand),
-    /// List templates of the current config
-    List,
+	/// List templates of the current config
+	List,
+	/// Removes the files of the last generation, unless they were edited since
+	Undo,
+	/// Merges the changes of a template into the files generated from it
+	Update(UpdateCommand),
+	/// Inspects the config resolved from every config file
+	Config(ConfigCommand),
+	/// Writes a commented config and a templates directory with an example template
+	Init(InitCommand),
+	/// Creates a template out of existing files, replacing a sample name with the `name` variable
+	NewTemplate(NewTemplateCommand),
+	/// Lists the variables and pipes a template uses, and which ones the config provides
+	Inspect(InspectCommand),
+	/// Checks templates for problems without generating them
+	Lint(LintCommand),
+}
+
+#[derive(Debug, Args)]
+pub struct LintCommand {
+	/// Template name to check, many templates can be separated by commas, defaults to every
+	/// template
+	pub template: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct InspectCommand {
+	/// Template name to inspect, many templates can be separated by commas
+	pub template: String,
 }
 
 #[derive(Debug, Args)]
+pub struct NewTemplateCommand {
+	/// Name of the template to create in the first templates directory
+	pub template: String,
+
+	/// Files or directories to create the template from
+	#[arg(long, required = true)]
+	pub from: Vec<PathBuf>,
+
+	/// Name used by the files, like `IconButton`, replaced in every case style
+	#[arg(long)]
+	pub name: String,
+}
+
+#[derive(Debug, Args)]
+pub struct InitCommand {
+	/// Where to write the config
+	#[arg(long, value_enum, default_value_t = InitLocation::Project)]
+	pub location: InitLocation,
+
+	/// Templates directory to create, relative to the config
+	#[arg(long, default_value = "templates")]
+	pub templates_path: String,
+
+	/// Overwrites an existing config
+	#[arg(long)]
+	pub force: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum InitLocation {
+	/// The current directory
+	Project,
+	/// The user-global config directory, like `~/.config/code-conjurer`
+	User,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigCommand {
+	#[clap(subcommand)]
+	pub command: ConfigCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommands {
+	/// Prints the effective config, merged from every config file, as TOML
+	Show,
+	/// Prints the config files that were read and where each setting came from
+	Sources,
+	/// Checks the config files for unknown keys and directories that do not exist
+	Validate,
+}
+
+#[derive(Debug, Args)]
+#[command(group(ArgGroup::new("output").args(["path", "name"])))]
 pub struct GenerateCommand {
-	/// Template name or path to generate
+	/// Template name or path to generate, many templates can be separated by commas
 	pub template: String,
 
-	/// Path for generating
+	/// Output path, its last component is the name and the rest is the output directory
+	#[arg(conflicts_with = "name")]
 	pub path: Option<String>,
+
+	/// Name of the output, available to the template as the `name` variable
+	#[arg(long)]
+	pub name: Option<String>,
+
+	/// Directory to generate into, defaults to the current directory. The output picker is not
+	/// used with it, so a path or a name is required
+	#[arg(long, requires = "output")]
+	pub out_dir: Option<String>,
 	// / Output file name
 	// pub output: String,
 	/// Aditional parameters for the template output
 	#[arg(short = 'p', value_parser = parse_key_val::<String, String>)]
 	pub params: Vec<(String, String)>,
+
+	/// Shows what would be generated without writing any file
+	#[arg(long)]
+	pub dry_run: bool,
+
+	/// Allows variables to write files outside of the output directory
+	#[arg(long)]
+	pub allow_outside_output: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct UpdateCommand {
+	/// Template of the generation to update, defaults to the last generation
+	pub template: Option<String>,
 }
 
 mod params_parser {
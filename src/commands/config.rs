@@ -0,0 +1,103 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use miette::IntoDiagnostic;
+use serde::Serialize;
+
+use crate::config::{
+	config_files, selected_profile, toml_config::Source, validate::validate, Config,
+};
+
+/// The effective config, in the shape of a config file
+#[derive(Serialize)]
+struct ConfigView {
+	templates_path: Vec<String>,
+	namespaces: BTreeMap<String, String>,
+	variables: BTreeMap<String, String>,
+}
+
+/// Prints the config merged from every config file, profile and environment variable, so it can
+/// be pasted into a config file
+pub fn print_config(config: &Config) -> miette::Result<()> {
+	let toml_config = &config.toml_config;
+	let to_string = |path: &PathBuf| path.to_string_lossy().into_owned();
+
+	let view = ConfigView {
+		templates_path: toml_config.templates_paths.iter().map(to_string).collect(),
+		namespaces: toml_config
+			.namespaces
+			.iter()
+			.map(|(namespace, path)| (namespace.clone(), to_string(path)))
+			.collect(),
+		variables: toml_config.variables.clone().into_iter().collect(),
+	};
+
+	let content = toml::to_string(&view).into_diagnostic()?;
+	print!("{content}");
+	Ok(())
+}
+
+/// Validates the config files looked up, or `config_file`, see [`validate`]. The config is not
+/// read first, it would stop at the first broken file.
+pub fn print_validation(
+	config_file: Option<PathBuf>,
+	profile: Option<String>,
+) -> miette::Result<()> {
+	let files = config_files(config_file)?;
+	validate(&files, selected_profile(profile).as_deref())?;
+	println!("Config is valid");
+	Ok(())
+}
+
+/// Prints every config file that was looked up, from the most global to the most local, found or
+/// not, and the file or environment variable each effective setting came from
+pub fn print_sources(config: &Config) {
+	let toml_config = &config.toml_config;
+	let sources = &toml_config.sources;
+
+	println!(
+		"Read {} of {} config files:",
+		sources.files.len(),
+		sources.considered.len()
+	);
+	for file in sources.considered.iter() {
+		match sources.files.contains(file) {
+			true => println!("{} ✓", file.to_string_lossy()),
+			false => println!("{} (not found)", file.to_string_lossy()),
+		}
+	}
+
+	println!();
+	for templates_path in toml_config.templates_paths.iter() {
+		let file = sources.templates_paths.get(templates_path);
+		println!(
+			"templates_path = {} ({})",
+			templates_path.to_string_lossy(),
+			source(file)
+		);
+	}
+
+	let mut namespaces = toml_config.namespaces.iter().collect::<Vec<_>>();
+	namespaces.sort();
+	for (namespace, path) in namespaces {
+		let file = sources.namespaces.get(namespace);
+		println!(
+			"namespaces.{namespace} = {} ({})",
+			path.to_string_lossy(),
+			source(file)
+		);
+	}
+
+	let mut variables = toml_config.variables.iter().collect::<Vec<_>>();
+	variables.sort();
+	for (name, value) in variables {
+		let file = sources.variables.get(name);
+		println!("{name} = {value:?} ({})", source(file));
+	}
+}
+
+fn source(source: Option<&Source>) -> String {
+	match source {
+		Some(source) => source.to_string(),
+		None => "default".to_owned(),
+	}
+}
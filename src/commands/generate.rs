@@ -1,33 +1,98 @@
 mod error;
+mod guard;
+mod inject;
+mod resolve;
 
 use std::{
 	collections::HashMap,
-	ffi::OsString,
-	fs::{self, FileType},
-	io::{self, BufRead, BufReader, BufWriter, Write},
+	ffi::{OsStr, OsString},
+	fs::{self, FileType, OpenOptions},
+	io::{self, Write},
 	os::unix::prelude::OsStringExt,
-	path::PathBuf,
+	path::{Component, Path, PathBuf},
 };
 
+use guard::escape_advice;
+
 use crate::{
 	config::Config,
 	io::path::NamedPathBuf,
-	template::parse::{DefaultTemplateParse, TemplateParse},
+	template::{
+		blocks,
+		condition::Condition,
+		manifest::{Manifest, RootLayout, WriteMode, MANIFEST_FILENAME},
+		parse::{DefaultTemplateParse, TemplateParse},
+	},
+};
+
+pub use error::Error;
+pub use guard::OutputGuard;
+pub use resolve::{
+	category_templates, is_category, Template, TemplateRoots, NAMESPACE_SEPARATOR,
+	TEMPLATES_SEPARATOR,
 };
 
-use error::Error;
+/// Variable set by the output name of every generation
+pub const NAME_VAR: &str = "name";
+
+#[derive(Debug, Default)]
+pub struct FilesGenerated {
+	pub created: Vec<PathBuf>,
+	/// Existing files that were replaced by the rendered content
+	pub overwritten: Vec<PathBuf>,
+	/// Existing files the rendered content was appended to
+	pub appended: Vec<PathBuf>,
+	/// Existing files a snippet was injected into
+	pub injected: Vec<PathBuf>,
+	/// Directories that did not exist before the generation, in creation order
+	pub directories: Vec<PathBuf>,
+	/// Directory the template was generated into
+	pub output: PathBuf,
+	/// Variables the template was rendered with
+	pub variables: HashMap<String, String>,
+	/// Template entries whose condition is false
+	pub skipped: Vec<Skipped>,
+}
 
-pub type FilesGenerated = Box<[PathBuf]>;
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+	/// Reports what would be generated without writing anything
+	pub dry_run: bool,
+	/// Allows writing outside of the output directory, like with a variable set to `../shared`
+	pub allow_outside_output: bool,
+}
+
+/// A template entry rendered in memory, not written yet
+#[derive(Debug)]
+pub enum Rendered {
+	Directory(PathBuf),
+	File {
+		path: PathBuf,
+		content: Vec<u8>,
+		mode: WriteMode,
+	},
+	Skipped(Skipped),
+}
+
+/// A template entry that is not generated because its condition is false
+#[derive(Debug)]
+pub struct Skipped {
+	/// Path of the entry relative to the template root
+	pub path: PathBuf,
+	pub condition: Condition,
+}
 
 /// Arguments:
 /// * `cli_variables`: arguments passed to cli like "namespace=foo, bar=baz",
-/// * `template_name`: name of template stored in user files, like "ng-c", "ng-s", "rc-c", etc
+/// * `template_name`: name of template stored in user files, like "ng-c", "ng-s", "rc-c", etc,
+///   or many of them separated by commas, like "rc-c,rc-test",
 /// * `output`: path where to generate the files,
 pub fn generate(
 	cli_variables: Vec<(String, String)>,
 	template_name: String,
 	mut output: PathBuf,
 	mut config: Config,
+	options: Options,
 ) -> Result<FilesGenerated, Error> {
 	let output_name = output
 		.file_name()
@@ -40,197 +105,524 @@ pub fn generate(
 		output_name.to_owned(),
 	);
 
-	let template_path = config.toml_config.templates_path.join(&template_name);
-	// Use a match to avoid borrow checker issues
-	let template_file_metadata = match template_path.metadata() {
-		Ok(metadata) => metadata,
-		Err(_) => {
-			let error = Error::template_not_found(template_name, config.toml_config.templates_path);
-			return Err(error);
-		}
-	};
-
-	let mut files_generated = vec![];
-	let parser = DefaultTemplateParse::with_vars(config.toml_config.variables);
+	let roots = TemplateRoots::from_config(&config.toml_config);
+	let templates = Template::resolve_all(&template_name, &roots)?;
 
-	if !template_file_metadata.file_type().is_dir() {
-		return Err(Error::template_invalid(template_name, template_path));
-	}
+	let mut files_generated = FilesGenerated {
+		variables: config.toml_config.variables.clone(),
+		..Default::default()
+	};
+	let parser =
+		DefaultTemplateParse::with_vars(config.toml_config.variables).with_partials(&roots.all());
 
 	// Create the files in the parent output directory
 	if !output.pop() {
 		return Err(Error::OutputNameInvalid); // TODO: Better error
 	}
 
-	if fs::create_dir_all(&output).is_err() {
+	// Render everything before writing, so a broken template does not leave half an output
+	let rendered = render_all(&templates, &output, &parser, &options)?;
+
+	// Injections are rendered over the rendered files, and written with them
+	let (mut injected, mut errors) = (vec![], vec![]);
+	let injections = templates
+		.iter()
+		.flat_map(|template| template.manifest.inject.iter());
+	for injection in injections {
+		match inject::render_injection(injection, &output, &parser, &rendered, &injected) {
+			Ok(Some(injection)) => injected.push(injection),
+			Ok(None) => {}
+			Err(error) => errors.push(error),
+		}
+	}
+	Error::collect(errors)?;
+
+	let guard = OutputGuard::new(&output, options.allow_outside_output, parser.vars())?;
+	let injected_paths = injected.iter().map(|injection| injection.target.as_path());
+	guard.check_all(rendered_paths(&rendered).chain(injected_paths))?;
+
+	let mut missing_dirs = output
+		.ancestors()
+		.take_while(|ancestor| !ancestor.as_os_str().is_empty() && !ancestor.exists())
+		.map(Path::to_path_buf)
+		.collect::<Vec<_>>();
+
+	if !options.dry_run && fs::create_dir_all(&output).is_err() {
 		return Err(Error::CouldNotWrite(output)); // Find better error
 	}
 
-	generate_dir(template_path, output, &mut files_generated, &parser)?;
+	missing_dirs.reverse();
+	files_generated.directories = missing_dirs;
 
-	Ok(files_generated.into_boxed_slice())
-}
+	write_rendered(rendered, &mut files_generated, options.dry_run)?;
 
-fn recursive_generate<T: TemplateParse>(
-	template_path: NamedPathBuf,
-	template_filetype: FileType,
-	output: NamedPathBuf,
-	files_generated: &mut Vec<PathBuf>,
+	files_generated.injected = inject::write_injected(injected, options.dry_run)?;
 
+	files_generated.output = output;
+	Ok(files_generated)
+}
+
+/// Renders every template of a composition, which must not render the same file twice
+pub fn render_all<T: TemplateParse>(
+	templates: &[Template],
+	output: &Path,
 	template_parser: &T,
-) -> Result<(), Error> {
-	if template_filetype.is_file() {
-		generate_file(
-			template_path.pathbuf,
-			output.pathbuf,
-			files_generated,
-			template_parser,
-		)
-	} else if template_filetype.is_dir() {
-		if matches!(fs::create_dir(&output.pathbuf), Err(e) if e.kind() != io::ErrorKind::AlreadyExists)
-		{
-			return Err(Error::CouldNotWrite(output.pathbuf)); // Find better error
-		}
+	options: &Options,
+) -> Result<Vec<Rendered>, Error> {
+	let mut rendered = vec![];
+	let mut rendered_by = HashMap::new();
+	let mut errors = vec![];
+	for template in templates.iter() {
+		let template_rendered = match render_template(template, output, template_parser, options) {
+			Ok(template_rendered) => template_rendered,
+			Err(error) => {
+				errors.push(error);
+				continue;
+			}
+		};
 
-		generate_dir(
-			template_path.pathbuf,
-			output.pathbuf,
-			files_generated,
-			template_parser,
-		)
-	} else {
-		unimplemented!("Symlinks are not supported yet! :(");
+		for entry in template_rendered.iter() {
+			let Rendered::File { path, .. } = entry else {
+				continue;
+			};
+			if let Some(first) = rendered_by.insert(path.clone(), &template.name) {
+				errors.push(Error::DuplicateOutput {
+					path: path.clone(),
+					first: first.clone(),
+					second: template.name.clone(),
+				});
+			}
+		}
+		rendered.extend(template_rendered);
 	}
+	Error::collect(errors)?;
+
+	Ok(rendered)
 }
 
-fn generate_dir<T: TemplateParse>(
-	template_dir: PathBuf,
-	output: PathBuf,
-	files_generated: &mut Vec<PathBuf>,
+/// Renders every entry of `template` as if it was generated into `output`, or into a directory
+/// of `output` named after the `name` variable, depending on the [`RootLayout`] of the template
+pub fn render_template<T: TemplateParse>(
+	template: &Template,
+	output: &Path,
 	template_parser: &T,
-) -> Result<(), Error> {
-	let read_dir = match template_dir.read_dir() {
-		Ok(read_dir) => read_dir,
-		Err(_) => {
-			let error = Error::CouldNotRead(template_dir);
-			return Err(error);
+	options: &Options,
+) -> Result<Vec<Rendered>, Error> {
+	let generation = Generation {
+		layers: &template.layers,
+		manifest: &template.manifest,
+		template_parser,
+		allow_outside_output: options.allow_outside_output,
+	};
+
+	let mut rendered = vec![];
+	let root = match template.manifest.root.unwrap_or_default() {
+		RootLayout::Parent => output.to_path_buf(),
+		RootLayout::Directory => {
+			let name = template_parser
+				.vars()
+				.get(NAME_VAR)
+				.ok_or(Error::OutputNameInvalid)?;
+			let root = output.join(name);
+			rendered.push(Rendered::Directory(root.clone()));
+			root
 		}
 	};
 
-	for entry in read_dir {
-		let entry = match entry {
-			Ok(entry) => entry,
-			Err(_) => {
-				let error = Error::CouldNotRead(template_dir);
-				return Err(error);
-			}
-		};
+	render_dir(&generation, Path::new(""), &root, &mut rendered)?;
 
-		let os_filename = entry.file_name();
-		let filename = match os_filename.to_str() {
-			Some(filename) => filename,
-			None => {
-				let error = Error::CouldNotRead(entry.path()); // TODO: Better error
-				return Err(error);
-			}
-		};
+	Ok(rendered)
+}
 
-		let mut parsed_filename = vec![];
-		if let Err(e) = template_parser.parse(filename, &mut parsed_filename) {
-			let error = Error::from_parse_error(e, filename.to_string(), filename.to_string()); // TODO
-			return Err(error);
-		};
+/// Renders a templated string that does not come from a template file, like manifest values
+fn render<T: TemplateParse>(
+	template_parser: &T,
+	content: &str,
+	filename: &str,
+) -> Result<String, Error> {
+	let mut rendered = vec![];
+	if let Err(e) = template_parser.parse(content, &mut rendered) {
+		return Err(Error::from_parse_error(
+			e,
+			content.to_owned(),
+			filename.to_owned(),
+		));
+	}
 
-		let parsed_filename = OsString::from_vec(parsed_filename);
+	Ok(String::from_utf8_lossy(&rendered).into_owned())
+}
 
-		// Do the filetype call in this scope because it is almost free in most platforms
-		let filetype = match entry.file_type() {
-			Ok(filetype) => filetype,
-			Err(_) => {
-				let error = Error::CouldNotRead(template_dir);
-				return Err(error);
-			}
-		};
+/// State shared by every entry of a single template generation
+struct Generation<'a, T: TemplateParse> {
+	/// Directories of the template and of the templates it extends, from the base to the template
+	layers: &'a [PathBuf],
+	manifest: &'a Manifest,
+	template_parser: &'a T,
+	allow_outside_output: bool,
+}
 
-		let output_named_path =
-			NamedPathBuf::new(output.join(&parsed_filename), parsed_filename.clone());
+/// An entry of a template directory, looked up in every layer of the template
+pub struct LayeredEntry {
+	pub filename: OsString,
+	pub filetype: FileType,
+	/// Paths of the entry in each layer that has it, from the base to the template
+	pub sources: Vec<PathBuf>,
+}
 
-		recursive_generate(
-			NamedPathBuf::new(entry.path(), os_filename),
-			filetype,
-			output_named_path,
-			files_generated,
-			template_parser,
-		)?;
+fn recursive_render<T: TemplateParse>(
+	generation: &Generation<T>,
+	entry: LayeredEntry,
+	relative_path: PathBuf,
+	output: NamedPathBuf,
+	mode: WriteMode,
+	rendered: &mut Vec<Rendered>,
+) -> Result<(), Error> {
+	if entry.filetype.is_file() {
+		let content = render_file(&entry.sources, generation.template_parser)?;
+		rendered.push(Rendered::File {
+			path: output.pathbuf,
+			content,
+			mode,
+		});
+
+		Ok(())
+	} else if entry.filetype.is_dir() {
+		rendered.push(Rendered::Directory(output.pathbuf.clone()));
+		render_dir(generation, &relative_path, &output.pathbuf, rendered)
+	} else {
+		unimplemented!("Symlinks are not supported yet! :(");
 	}
+}
 
-	Ok(())
+fn render_dir<T: TemplateParse>(
+	generation: &Generation<T>,
+	relative_dir: &Path,
+	output: &Path,
+	rendered: &mut Vec<Rendered>,
+) -> Result<(), Error> {
+	// Entries after a broken one are still rendered, to report every error at once
+	let mut errors = vec![];
+	for entry in layered_entries(generation.layers, relative_dir)? {
+		if let Err(error) = render_entry(generation, entry, relative_dir, output, rendered) {
+			errors.push(error);
+		}
+	}
+
+	Error::collect(errors)
 }
 
-fn generate_file<T: TemplateParse>(
-	template_filename: PathBuf,
-	output_filename: PathBuf,
-	generated_files: &mut Vec<PathBuf>,
-	template_parser: &T,
+fn render_entry<T: TemplateParse>(
+	generation: &Generation<T>,
+	entry: LayeredEntry,
+	relative_dir: &Path,
+	output: &Path,
+	rendered: &mut Vec<Rendered>,
 ) -> Result<(), Error> {
-	let template_file = match std::fs::File::open(&template_filename) {
-		Ok(file) => file,
-		Err(_) => {
-			let error = Error::NotOpenable(template_filename);
+	let filename = match entry.filename.to_str() {
+		Some(filename) => filename,
+		None => {
+			let error = Error::CouldNotRead(relative_dir.join(&entry.filename)); // TODO: Better error
 			return Err(error);
 		}
 	};
-	let mut template_file = BufReader::new(template_file);
 
-	let output_file = match std::fs::File::create(&output_filename) {
-		Ok(file) => file,
-		Err(_) => {
-			let error = Error::CouldNotWrite(output_filename);
+	if filename == MANIFEST_FILENAME {
+		return Ok(());
+	}
+
+	let relative_path = relative_dir.join(filename);
+	let settings = generation
+		.manifest
+		.entry_settings(&relative_path, filename)?;
+
+	// Checked before parsing the filename, it may use the variables of the condition
+	if let Some(condition) = settings.condition {
+		if !condition.evaluate(generation.template_parser.vars()) {
+			rendered.push(Rendered::Skipped(Skipped {
+				path: relative_path,
+				condition,
+			}));
+			return Ok(());
+		}
+	}
+
+	let (mode, filename) = (settings.mode, settings.filename);
+
+	let mut parsed_filename = vec![];
+	if let Err(e) = generation
+		.template_parser
+		.parse(filename, &mut parsed_filename)
+	{
+		let error = Error::from_parse_error(e, filename.to_string(), filename.to_string()); // TODO
+
+		// Rendered anyway, to report the errors inside of the entry too
+		let output_path = NamedPathBuf::new(output.join(filename), filename.into());
+		let inner = recursive_render(
+			generation,
+			entry,
+			relative_path,
+			output_path,
+			mode,
+			&mut vec![],
+		);
+		return Error::collect([error].into_iter().chain(inner.err()).collect());
+	};
+
+	let parsed_filename = OsString::from_vec(parsed_filename);
+	let allow_escape = generation.allow_outside_output;
+	let components = match nested_components(Path::new(&parsed_filename), allow_escape) {
+		Some(components) => components,
+		None => {
+			let rendered = parsed_filename.to_string_lossy().into_owned();
+			let vars = generation.template_parser.vars();
+			let error = Error::PathEscapes {
+				filename: filename.to_owned(),
+				advice: escape_advice(&rendered, vars),
+				rendered,
+			};
 			return Err(error);
 		}
 	};
-	let mut output_file = BufWriter::new(output_file);
 
-	let mut line = String::new();
-	loop {
-		let bytes = match template_file.read_line(&mut line) {
-			Ok(line) => line,
+	// Intermediate directories of a name like `features/auth/{(name)}.ts`
+	let mut output_path = output.to_path_buf();
+	let (last, intermediate) = components.split_last().expect("Components are never empty");
+	for component in intermediate {
+		output_path.push(component);
+		rendered.push(Rendered::Directory(output_path.clone()));
+	}
+
+	output_path.push(last);
+	let output_named_path = NamedPathBuf::new(output_path, last.to_os_string());
+
+	recursive_render(
+		generation,
+		entry,
+		relative_path,
+		output_named_path,
+		mode,
+		rendered,
+	)
+}
+
+/// Splits a rendered file name on `/`, so variables like `path=features/auth` create nested
+/// directories. Returns `None` for names that do not end with a normal component, and for names
+/// with `..` or a root, which would escape the directory they are rendered in, unless allowed.
+fn nested_components(rendered: &Path, allow_escape: bool) -> Option<Vec<&OsStr>> {
+	let mut components = vec![];
+	for component in rendered.components() {
+		match component {
+			Component::Normal(_) => {}
+			Component::CurDir => continue,
+			Component::ParentDir | Component::RootDir | Component::Prefix(_) if !allow_escape => {
+				return None
+			}
+			_ => {}
+		}
+		components.push(component.as_os_str());
+	}
+
+	match rendered.components().next_back() {
+		Some(Component::Normal(_)) => Some(components),
+		_ => None,
+	}
+}
+
+/// Reads `relative_dir` in every layer, entries of later layers are merged with the entries of
+/// the same name and kind of earlier layers, and replace them if their kind differs
+pub fn layered_entries(
+	layers: &[PathBuf],
+	relative_dir: &Path,
+) -> Result<Vec<LayeredEntry>, Error> {
+	let mut entries: Vec<LayeredEntry> = vec![];
+
+	for layer in layers {
+		let template_dir = layer.join(relative_dir);
+		let read_dir = match template_dir.read_dir() {
+			Ok(read_dir) => read_dir,
+			Err(e) if e.kind() == io::ErrorKind::NotFound && layers.len() > 1 => continue,
 			Err(_) => {
-				let error = Error::CouldNotRead(template_filename);
+				let error = Error::CouldNotRead(template_dir);
 				return Err(error);
 			}
 		};
 
-		// EOF
-		if bytes == 0 {
-			break;
+		for entry in read_dir {
+			let entry = match entry {
+				Ok(entry) => entry,
+				Err(_) => {
+					let error = Error::CouldNotRead(template_dir);
+					return Err(error);
+				}
+			};
+
+			// Do the filetype call in this scope because it is almost free in most platforms
+			let filetype = match entry.file_type() {
+				Ok(filetype) => filetype,
+				Err(_) => {
+					let error = Error::CouldNotRead(template_dir);
+					return Err(error);
+				}
+			};
+
+			let filename = entry.file_name();
+			let layered = LayeredEntry {
+				filename: filename.clone(),
+				filetype,
+				sources: vec![entry.path()],
+			};
+
+			match entries.iter_mut().find(|entry| entry.filename == filename) {
+				Some(existing) if existing.filetype.is_dir() == filetype.is_dir() => {
+					existing.sources.push(entry.path())
+				}
+				Some(existing) => *existing = layered,
+				None => entries.push(layered),
+			}
 		}
+	}
 
-		if let Err(e) = template_parser.parse(&line, &mut output_file) {
+	Ok(entries)
+}
+
+/// Renders a file from its sources, each source extends the previous one
+fn render_file<T: TemplateParse>(
+	sources: &[PathBuf],
+	template_parser: &T,
+) -> Result<Vec<u8>, Error> {
+	let (source, template_filename) = layered_source(sources)?;
+
+	let mut content = vec![];
+	let mut errors = vec![];
+	for line in source.split_inclusive('\n') {
+		if let Err(e) = template_parser.parse(line, &mut content) {
 			let error = Error::from_parse_error(
 				e,
-				line,
+				line.to_owned(),
 				template_filename
+					.clone()
 					.into_os_string()
 					.into_string()
 					.unwrap_or_else(|_| "<Invalid Filename>".into()),
 			);
 
-			return Err(error);
+			errors.push(error);
 		};
+	}
+
+	Error::collect(errors)?;
+	Ok(content)
+}
 
-		line.clear();
+/// Extends each source of a file with the next one, returns the content without blocks, still
+/// to be parsed, and the path of the last source
+pub fn layered_source(sources: &[PathBuf]) -> Result<(String, &PathBuf), Error> {
+	let mut source = String::new();
+	for (i, template_filename) in sources.iter().enumerate() {
+		let layer = match fs::read_to_string(template_filename) {
+			Ok(layer) => layer,
+			Err(_) => return Err(Error::NotOpenable(template_filename.clone())),
+		};
+
+		source = if i == 0 {
+			layer
+		} else {
+			match blocks::extend(&source, &layer) {
+				Ok(extended) => extended,
+				Err(e) => return Err(Error::from_block_error(e, layer, template_filename)),
+			}
+		};
 	}
 
-	if let Err(e) = output_file.flush() {
-		match e.kind() {
-			std::io::ErrorKind::UnexpectedEof => {}
-			_ => return Err(Error::CouldNotWrite(output_filename)),
+	// Sources are never empty, each entry is found in at least one layer
+	let template_filename = &sources[sources.len() - 1];
+	let source = match blocks::strip_blocks(&source) {
+		Ok(stripped) => stripped,
+		Err(e) => return Err(Error::from_block_error(e, source, template_filename)),
+	};
+
+	Ok((source, template_filename))
+}
+
+/// Paths of the directories and files in `rendered`
+pub fn rendered_paths(rendered: &[Rendered]) -> impl Iterator<Item = &Path> {
+	rendered.iter().filter_map(|entry| match entry {
+		Rendered::Directory(path) | Rendered::File { path, .. } => Some(path.as_path()),
+		Rendered::Skipped(_) => None,
+	})
+}
+
+fn write_rendered(
+	rendered: Vec<Rendered>,
+	files_generated: &mut FilesGenerated,
+	dry_run: bool,
+) -> Result<(), Error> {
+	for entry in rendered {
+		match entry {
+			Rendered::Directory(path) if dry_run => {
+				// Nested file names may render the same directory many times
+				if !path.exists() && !files_generated.directories.contains(&path) {
+					files_generated.directories.push(path);
+				}
+			}
+			Rendered::Directory(path) => match fs::create_dir(&path) {
+				Ok(_) => files_generated.directories.push(path),
+				Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+				Err(_) => return Err(Error::CouldNotWrite(path)), // Find better error
+			},
+			Rendered::File {
+				path,
+				content,
+				mode,
+			} => write_file(path, &content, mode, files_generated, dry_run)?,
+			Rendered::Skipped(skipped) => files_generated.skipped.push(skipped),
 		}
 	}
 
+	Ok(())
+}
+
+fn write_file(
+	output_filename: PathBuf,
+	content: &[u8],
+	mode: WriteMode,
+	generated_files: &mut FilesGenerated,
+	dry_run: bool,
+) -> Result<(), Error> {
+	let existed = output_filename.exists();
+	if !dry_run {
+		write_content(&output_filename, content, mode)?;
+	}
+
 	// Push the generated file is this scope to avoid cloning the path
-	generated_files.push(output_filename);
+	match (mode, existed) {
+		(_, false) => generated_files.created.push(output_filename),
+		(WriteMode::Overwrite, true) => generated_files.overwritten.push(output_filename),
+		(WriteMode::Append, true) => generated_files.appended.push(output_filename),
+	}
+
+	Ok(())
+}
+
+fn write_content(output_filename: &Path, content: &[u8], mode: WriteMode) -> Result<(), Error> {
+	let output_file = match mode {
+		WriteMode::Overwrite => std::fs::File::create(output_filename),
+		WriteMode::Append => OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(output_filename),
+	};
+	let mut output_file = match output_file {
+		Ok(file) => file,
+		Err(_) => {
+			let error = Error::CouldNotWrite(output_filename.to_owned());
+			return Err(error);
+		}
+	};
+
+	if output_file.write_all(content).is_err() {
+		return Err(Error::CouldNotWrite(output_filename.to_owned()));
+	}
 
 	Ok(())
 }
@@ -241,7 +633,7 @@ fn attatch_variables(
 	output_name: String,
 ) {
 	if !files_variables.contains_key(&output_name) {
-		files_variables.insert("name".into(), output_name);
+		files_variables.insert(NAME_VAR.into(), output_name);
 	}
 
 	for (key, value) in cli_variables {
@@ -255,7 +647,7 @@ mod tests {
 
 	use crate::config::toml_config::TomlConfig;
 
-	use super::*;
+	use super::{error::TemplateError, *};
 
 	#[test]
 	fn should_attach_variables() {
@@ -281,15 +673,23 @@ mod tests {
 
 		let config = Config {
 			toml_config: TomlConfig {
-				templates_path: PathBuf::from("./files/templates/"),
+				templates_paths: vec![PathBuf::from("./files/templates/")],
 				variables,
+				..Default::default()
 			},
 		};
 
 		let _ = fs::create_dir_all("./files/templates/foo");
 		fs::write("./files/templates/foo/foo", "{(namespace)}").unwrap();
 		let _ = fs::remove_dir_all("./files/templates/foo/foo");
-		generate(cli_variables, template_name, output, config).unwrap();
+		generate(
+			cli_variables,
+			template_name,
+			output,
+			config,
+			Default::default(),
+		)
+		.unwrap();
 
 		let contents = fs::read_to_string("./files/output/foo").unwrap();
 		assert_eq!(contents, "app");
@@ -305,21 +705,59 @@ mod tests {
 
 		let config = Config {
 			toml_config: TomlConfig {
-				templates_path: PathBuf::from("./files/templates/"),
+				templates_paths: vec![PathBuf::from("./files/templates/")],
 				variables,
+				..Default::default()
 			},
 		};
 
 		let _ = fs::create_dir_all("./files/templates/");
 		fs::write("./files/templates/temp2", "").unwrap();
 
-		let error = generate(vec![], template_name, output, config).unwrap_err();
+		let error = generate(vec![], template_name, output, config, Default::default()).unwrap_err();
 		assert!(
 			matches!(error, Error::TemplateNotValid { .. }),
 			"Error generated was: {error:?}"
 		);
 	}
 
+	#[test]
+	fn should_report_every_error() {
+		let template = PathBuf::from("./files/templates/broken/nested");
+		fs::create_dir_all(&template).unwrap();
+		fs::write(
+			template.join("{(missing)}.ts"),
+			"{(name|nope)} {(other)}\n{(name)}",
+		)
+		.unwrap();
+		fs::write(template.join("b.ts"), "{(namespace)} {(last)}").unwrap();
+
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![PathBuf::from("./files/templates/")],
+				variables: HashMap::from([("namespace".to_owned(), "app".to_owned())]),
+				..Default::default()
+			},
+		};
+		let output = PathBuf::from("./files/output/broken");
+
+		let error = generate(vec![], "broken".into(), output, config, Default::default()).unwrap_err();
+		let Error::Many { errors } = error else {
+			panic!("A single error was reported: {error:?}");
+		};
+		let mut errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+		errors.sort();
+		assert_eq!(
+			errors,
+			vec![
+				"pipe 'nope' not found",
+				"variable not found",
+				"variable not found",
+				"variable not found",
+			]
+		);
+	}
+
 	#[test]
 	fn should_parse_filenames() {
 		let cli_variables = vec![];
@@ -330,8 +768,9 @@ mod tests {
 
 		let config = Config {
 			toml_config: TomlConfig {
-				templates_path: PathBuf::from("./files/templates/"),
+				templates_paths: vec![PathBuf::from("./files/templates/")],
 				variables,
+				..Default::default()
 			},
 		};
 
@@ -343,7 +782,14 @@ mod tests {
 		)
 		.expect("Generate filename_template");
 
-		generate(cli_variables, template_name, output, config).unwrap();
+		generate(
+			cli_variables,
+			template_name,
+			output,
+			config,
+			Default::default(),
+		)
+		.unwrap();
 		let contents =
 			fs::read_to_string("./files/output/myoutput.txt").expect("Reading myoutput file");
 		assert_eq!(contents, "message");
@@ -353,8 +799,9 @@ mod tests {
 	fn should_generate_recursively() {
 		let config = Config {
 			toml_config: TomlConfig {
-				templates_path: PathBuf::from("./files/templates/"),
+				templates_paths: vec![PathBuf::from("./files/templates/")],
 				variables: HashMap::new(),
+				..Default::default()
 			},
 		};
 		let cli_variables = vec![];
@@ -375,6 +822,7 @@ mod tests {
 			"parent_dir".to_owned(),
 			PathBuf::from("./files/output/item1"),
 			config,
+			Default::default(),
 		)
 		.unwrap();
 
@@ -386,4 +834,538 @@ mod tests {
 			.expect("Child file content not match");
 		assert_eq!(child_content, "message");
 	}
+
+	#[test]
+	fn should_inject_once() {
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![PathBuf::from("./files/templates/")],
+				variables: HashMap::new(),
+				..Default::default()
+			},
+		};
+
+		fs::create_dir_all("./files/templates/injected").expect("Creating injected template");
+		fs::write("./files/templates/injected/{(name)}.rs", "").expect("Writing template file");
+		fs::write(
+			"./files/templates/injected/.template.toml",
+			"[[inject]]\ntarget = \"mod.rs\"\ncontent = \"mod {(name)};\"\nanchor = \"// modules\"\n",
+		)
+		.expect("Writing manifest");
+
+		fs::create_dir_all("./files/output/injected").expect("Creating output");
+		fs::write("./files/output/injected/mod.rs", "// modules\n").expect("Writing mod.rs");
+
+		for _ in 0..2 {
+			let config = Config {
+				toml_config: TomlConfig {
+					templates_paths: config.toml_config.templates_paths.clone(),
+					variables: HashMap::new(),
+					..Default::default()
+				},
+			};
+
+			generate(
+				vec![],
+				"injected".to_owned(),
+				PathBuf::from("./files/output/injected/item"),
+				config,
+				Default::default(),
+			)
+			.unwrap();
+		}
+
+		let content = fs::read_to_string("./files/output/injected/mod.rs").unwrap();
+		assert_eq!(content, "// modules\nmod item;\n");
+		assert!(!PathBuf::from("./files/output/injected/.template.toml").exists());
+	}
+
+	#[test]
+	fn should_not_write_when_injection_fails() {
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![PathBuf::from("./files/templates/")],
+				variables: HashMap::new(),
+				..Default::default()
+			},
+		};
+
+		fs::create_dir_all("./files/templates/injected_missing").expect("Creating template");
+		fs::write("./files/templates/injected_missing/{(name)}.rs", "").expect("Writing file");
+		fs::write(
+			"./files/templates/injected_missing/.template.toml",
+			"[[inject]]\ntarget = \"mod.rs\"\ncontent = \"mod {(name)};\"\n",
+		)
+		.expect("Writing manifest");
+
+		let output = PathBuf::from("./files/output/injected_missing/item");
+		let result = generate(
+			vec![],
+			"injected_missing".to_owned(),
+			output,
+			config,
+			Default::default(),
+		);
+
+		assert!(matches!(result, Err(Error::InjectTargetNotFound(_))));
+		assert!(!PathBuf::from("./files/output/injected_missing/item.rs").exists());
+	}
+
+	#[test]
+	fn should_not_write_when_a_path_escapes() {
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![PathBuf::from("./files/templates/")],
+				variables: HashMap::new(),
+				..Default::default()
+			},
+		};
+
+		fs::create_dir_all("./files/templates/escaping/link").expect("Creating template");
+		fs::write("./files/templates/escaping/a.txt", "a").expect("Writing file");
+		fs::write("./files/templates/escaping/link/b.txt", "b").expect("Writing file");
+
+		// A directory of the output linking outside of it
+		let _ = fs::remove_dir_all("./files/output/escaping");
+		fs::create_dir_all("./files/output/escaping").expect("Creating output");
+		fs::create_dir_all("./files/escaped").expect("Creating link target");
+		let target = fs::canonicalize("./files/escaped").unwrap();
+		std::os::unix::fs::symlink(target, "./files/output/escaping/link").expect("Linking");
+
+		let output = PathBuf::from("./files/output/escaping/item");
+		let result = generate(
+			vec![],
+			"escaping".to_owned(),
+			output,
+			config,
+			Default::default(),
+		);
+
+		assert!(matches!(result, Err(Error::OutsideOutput { .. })));
+		assert!(!PathBuf::from("./files/output/escaping/a.txt").exists());
+		assert!(!PathBuf::from("./files/escaped/b.txt").exists());
+	}
+
+	#[test]
+	fn should_append_to_existing_files() {
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![PathBuf::from("./files/templates/")],
+				variables: HashMap::new(),
+				..Default::default()
+			},
+		};
+
+		fs::create_dir_all("./files/templates/appended").expect("Creating appended template");
+		fs::write(
+			"./files/templates/appended/CHANGELOG.md.append",
+			"- Added {(name)}\n",
+		)
+		.expect("Writing template file");
+
+		fs::create_dir_all("./files/output/appended").expect("Creating output");
+		fs::write("./files/output/appended/CHANGELOG.md", "# Changelog\n").expect("Writing changelog");
+
+		let files = generate(
+			vec![],
+			"appended".to_owned(),
+			PathBuf::from("./files/output/appended/item"),
+			config,
+			Default::default(),
+		)
+		.unwrap();
+
+		assert_eq!(files.appended.len(), 1);
+		let content = fs::read_to_string("./files/output/appended/CHANGELOG.md").unwrap();
+		assert_eq!(content, "# Changelog\n- Added item\n");
+	}
+
+	#[test]
+	fn should_compose_templates() {
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![PathBuf::from("./files/templates/")],
+				variables: HashMap::new(),
+				..Default::default()
+			},
+		};
+
+		for name in ["composed_a", "composed_b", "composed_c"] {
+			fs::create_dir_all(format!("./files/templates/{name}")).expect("Creating template");
+			fs::write(format!("./files/templates/{name}/{{(name)}}.{name}"), name)
+				.expect("Writing template file");
+		}
+		fs::write(
+			"./files/templates/composed_b/.template.toml",
+			"compose = [\"composed_c\", \"composed_a\"]",
+		)
+		.expect("Writing manifest");
+		let _ = fs::remove_dir_all("./files/output/composed");
+
+		let files = generate(
+			vec![],
+			"composed_a,composed_b".to_owned(),
+			PathBuf::from("./files/output/composed/item"),
+			config,
+			Default::default(),
+		)
+		.unwrap();
+
+		assert_eq!(files.created.len(), 3);
+		for name in ["composed_a", "composed_b", "composed_c"] {
+			let content = fs::read_to_string(format!("./files/output/composed/item.{name}")).unwrap();
+			assert_eq!(content, name);
+		}
+	}
+
+	#[test]
+	fn should_not_write_files_rendered_twice() {
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![PathBuf::from("./files/templates/")],
+				variables: HashMap::new(),
+				..Default::default()
+			},
+		};
+
+		for name in ["duplicate_a", "duplicate_b"] {
+			fs::create_dir_all(format!("./files/templates/{name}")).expect("Creating template");
+			fs::write(format!("./files/templates/{name}/{{(name)}}.md"), name)
+				.expect("Writing template file");
+		}
+		let _ = fs::remove_dir_all("./files/output/duplicate");
+
+		let error = generate(
+			vec![],
+			"duplicate_a,duplicate_b".to_owned(),
+			PathBuf::from("./files/output/duplicate/item"),
+			config,
+			Default::default(),
+		)
+		.unwrap_err();
+
+		assert!(matches!(
+			error,
+			Error::DuplicateOutput { first, second, .. } if first == "duplicate_a" && second == "duplicate_b"
+		));
+		assert!(!PathBuf::from("./files/output/duplicate").exists());
+	}
+
+	#[test]
+	fn should_detect_composition_cycles() {
+		for name in ["cycle_a", "cycle_b"] {
+			fs::create_dir_all(format!("./files/templates/{name}")).expect("Creating template");
+		}
+		fs::write(
+			"./files/templates/cycle_a/.template.toml",
+			"compose = [\"cycle_b\"]",
+		)
+		.unwrap();
+		fs::write(
+			"./files/templates/cycle_b/.template.toml",
+			"compose = [\"cycle_a\"]",
+		)
+		.unwrap();
+
+		let roots = TemplateRoots {
+			paths: vec![PathBuf::from("./files/templates/")],
+			..Default::default()
+		};
+		let error = Template::resolve_all("cycle_a", &roots).unwrap_err();
+		assert!(
+			matches!(&error, Error::CompositionCycle(cycle) if cycle == "cycle_a -> cycle_b -> cycle_a"),
+			"Error generated was: {error:?}"
+		);
+	}
+
+	#[test]
+	fn should_extend_base_templates() {
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![PathBuf::from("./files/templates/")],
+				variables: HashMap::new(),
+				..Default::default()
+			},
+		};
+
+		fs::create_dir_all("./files/templates/extended_base").expect("Creating base template");
+		fs::create_dir_all("./files/templates/extended_child").expect("Creating child template");
+		fs::write(
+			"./files/templates/extended_base/{(name)}.ts",
+			"{(#block imports)}\nimport a;\n{(/block)}\nclass {(name)} {}\n",
+		)
+		.unwrap();
+		fs::write("./files/templates/extended_base/README.md", "base").unwrap();
+		fs::write(
+			"./files/templates/extended_child/{(name)}.ts",
+			"{(#block imports)}\nimport b;\n{(/block)}\n",
+		)
+		.unwrap();
+		fs::write("./files/templates/extended_child/README.md", "child").unwrap();
+		fs::write(
+			"./files/templates/extended_child/.template.toml",
+			"extends = \"extended_base\"",
+		)
+		.unwrap();
+
+		generate(
+			vec![],
+			"extended_child".to_owned(),
+			PathBuf::from("./files/output/extended/item"),
+			config,
+			Default::default(),
+		)
+		.unwrap();
+
+		let content = fs::read_to_string("./files/output/extended/item.ts").unwrap();
+		assert_eq!(content, "import b;\nclass item {}\n");
+		let readme = fs::read_to_string("./files/output/extended/README.md").unwrap();
+		assert_eq!(readme, "child");
+	}
+
+	#[test]
+	fn should_include_partials() {
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![PathBuf::from("./files/templates/")],
+				variables: HashMap::from([("author".into(), "me".into())]),
+				..Default::default()
+			},
+		};
+
+		fs::create_dir_all("./files/templates/_partials").expect("Creating partials");
+		fs::create_dir_all("./files/templates/with_partial").expect("Creating template");
+		fs::write("./files/templates/_partials/license", "// by {(author)}\n").unwrap();
+		fs::write(
+			"./files/templates/with_partial/{(name)}.rs",
+			"{(> license)}fn {(name)}() {}\n",
+		)
+		.unwrap();
+
+		generate(
+			vec![],
+			"with_partial".to_owned(),
+			PathBuf::from("./files/output/with_partial/item"),
+			config,
+			Default::default(),
+		)
+		.unwrap();
+
+		let content = fs::read_to_string("./files/output/with_partial/item.rs").unwrap();
+		assert_eq!(content, "// by me\nfn item() {}\n");
+	}
+
+	#[test]
+	fn should_detect_include_cycles() {
+		fs::create_dir_all("./files/templates/_partials").expect("Creating partials");
+		fs::write("./files/templates/_partials/cycle_a", "{(> cycle_b)}").unwrap();
+		fs::write("./files/templates/_partials/cycle_b", "{(> cycle_a)}").unwrap();
+
+		let parser = DefaultTemplateParse::with_vars(HashMap::new())
+			.with_partials(&[PathBuf::from("./files/templates/")]);
+		let error = render(&parser, "x {(> cycle_a)}", "file").unwrap_err();
+
+		let Error::Template(TemplateError::PartialInvalid { error, span, .. }) = error else {
+			panic!("Error generated was: {error:?}");
+		};
+		assert_eq!(span, (2, 13));
+		assert!(matches!(&error[0], TemplateError::PartialInvalid { .. }));
+	}
+
+	#[test]
+	fn should_skip_conditional_entries() {
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![PathBuf::from("./files/templates/")],
+				variables: HashMap::from([("with_tests".into(), "false".into())]),
+				..Default::default()
+			},
+		};
+
+		fs::create_dir_all("./files/templates/conditional").expect("Creating template");
+		fs::write("./files/templates/conditional/{(name)}.rs", "").unwrap();
+		fs::write(
+			"./files/templates/conditional/{(name)}.test.rs[if with_tests]",
+			"",
+		)
+		.unwrap();
+		fs::write("./files/templates/conditional/{(name)}.md", "").unwrap();
+		fs::write(
+			"./files/templates/conditional/.template.toml",
+			"[files.\"{(name)}.md\"]\nwhen = \"!with_tests\"",
+		)
+		.unwrap();
+
+		let files = generate(
+			vec![],
+			"conditional".to_owned(),
+			PathBuf::from("./files/output/conditional/item"),
+			config,
+			Options {
+				dry_run: true,
+				..Default::default()
+			},
+		)
+		.unwrap();
+
+		let mut created = files.created.clone();
+		created.sort();
+		assert_eq!(
+			created,
+			vec![
+				PathBuf::from("./files/output/conditional/item.md"),
+				PathBuf::from("./files/output/conditional/item.rs"),
+			]
+		);
+		assert_eq!(files.skipped.len(), 1);
+		assert_eq!(files.skipped[0].condition.to_string(), "with_tests");
+		assert!(!Path::new("./files/output/conditional").exists());
+	}
+
+	#[test]
+	fn should_nest_rendered_paths() {
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![PathBuf::from("./files/templates/")],
+				variables: HashMap::from([("path".into(), "features/auth".into())]),
+				..Default::default()
+			},
+		};
+
+		fs::create_dir_all("./files/templates/nested").expect("Creating template");
+		fs::write("./files/templates/nested/{(path)}.{(name)}.ts", "{(name)}").unwrap();
+		let _ = fs::remove_dir_all("./files/output/nested");
+
+		let files = generate(
+			vec![],
+			"nested".to_owned(),
+			PathBuf::from("./files/output/nested/item"),
+			config,
+			Default::default(),
+		)
+		.unwrap();
+
+		let content = fs::read_to_string("./files/output/nested/features/auth.item.ts").unwrap();
+		assert_eq!(content, "item");
+		assert!(files
+			.directories
+			.contains(&PathBuf::from("./files/output/nested/features")));
+	}
+
+	#[test]
+	fn should_refuse_escaping_paths() {
+		assert!(nested_components(Path::new("a/./b"), false).is_some());
+		assert!(nested_components(Path::new("../b"), false).is_none());
+		assert!(nested_components(Path::new("/etc/b"), false).is_none());
+		assert!(nested_components(Path::new(""), false).is_none());
+		assert!(nested_components(Path::new("../b"), true).is_some());
+		assert!(nested_components(Path::new("b/.."), true).is_none());
+	}
+
+	#[test]
+	fn should_generate_into_named_directories() {
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![PathBuf::from("./files/templates/")],
+				variables: HashMap::new(),
+				..Default::default()
+			},
+		};
+
+		fs::create_dir_all("./files/templates/directory_root").expect("Creating template");
+		fs::write("./files/templates/directory_root/index.ts", "{(name)}").unwrap();
+		fs::write(
+			"./files/templates/directory_root/.template.toml",
+			"root = \"directory\"",
+		)
+		.unwrap();
+
+		let files = generate(
+			vec![],
+			"directory_root".to_owned(),
+			PathBuf::from("./files/output/directory_root/Button"),
+			config,
+			Default::default(),
+		)
+		.unwrap();
+
+		let content = fs::read_to_string("./files/output/directory_root/Button/index.ts").unwrap();
+		assert_eq!(content, "Button");
+		assert_eq!(files.output, PathBuf::from("./files/output/directory_root"));
+	}
+
+	#[test]
+	fn should_shadow_templates_of_later_paths() {
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![
+					PathBuf::from("./files/templates/project/"),
+					PathBuf::from("./files/templates/personal/"),
+				],
+				variables: HashMap::new(),
+				..Default::default()
+			},
+		};
+
+		fs::create_dir_all("./files/templates/project/shadowed").expect("Creating template");
+		fs::create_dir_all("./files/templates/personal/shadowed").expect("Creating template");
+		fs::create_dir_all("./files/templates/personal/personal_only").expect("Creating template");
+		fs::write("./files/templates/project/shadowed/{(name)}", "project").unwrap();
+		fs::write("./files/templates/personal/shadowed/{(name)}", "personal").unwrap();
+		fs::write("./files/templates/personal/personal_only/{(name)}.md", "").unwrap();
+
+		generate(
+			vec![],
+			"shadowed,personal_only".to_owned(),
+			PathBuf::from("./files/output/shadowed/item"),
+			config,
+			Default::default(),
+		)
+		.unwrap();
+
+		let content = fs::read_to_string("./files/output/shadowed/item").unwrap();
+		assert_eq!(content, "project");
+		assert!(Path::new("./files/output/shadowed/item.md").exists());
+	}
+
+	#[test]
+	fn should_resolve_namespaced_templates() {
+		fs::create_dir_all("./files/templates/team/react/component").expect("Creating template");
+
+		let roots = TemplateRoots {
+			paths: vec![PathBuf::from("./files/templates/")],
+			namespaces: HashMap::from([("team".into(), PathBuf::from("./files/templates/team"))]),
+		};
+
+		let template = Template::resolve("team:react/component".to_owned(), &roots).unwrap();
+		assert_eq!(
+			template.layers,
+			vec![PathBuf::from("./files/templates/team/react/component")]
+		);
+
+		let error = Template::resolve("other:component".to_owned(), &roots).unwrap_err();
+		assert!(matches!(error, Error::NamespaceNotFound { .. }));
+	}
+
+	#[test]
+	fn should_resolve_templates_of_categories() {
+		for template in ["component", "hooks/state"] {
+			let template_dir = format!("./files/templates/category/react/{template}");
+			fs::create_dir_all(&template_dir).expect("Creating template");
+			fs::write(format!("{template_dir}/.template.toml"), "").unwrap();
+		}
+
+		let roots = TemplateRoots {
+			paths: vec![PathBuf::from("./files/templates/category")],
+			..Default::default()
+		};
+
+		assert!(Template::resolve("react/component".to_owned(), &roots).is_ok());
+
+		let error = Template::resolve("react".to_owned(), &roots).unwrap_err();
+		let Error::TemplateIsCategory { templates, .. } = error else {
+			panic!("Error generated was: {error:?}");
+		};
+		assert_eq!(templates, "component, hooks/state");
+	}
 }
@@ -1,8 +1,21 @@
-use std::{fmt::Display, path::PathBuf};
+use std::{
+	fmt::Display,
+	path::{Path, PathBuf},
+};
 
 use miette::Diagnostic;
 
-use crate::template::parse::error::{self as parse_error, PipeUndefined};
+use super::resolve::{category_templates, is_category};
+
+use crate::template::{
+	blocks::BlockError,
+	condition::ConditionError,
+	manifest::{ManifestError, Position},
+	parse::{
+		error::{self as parse_error, PipeUndefined},
+		PARTIALS_DIR,
+	},
+};
 
 #[derive(thiserror::Error, Debug, Diagnostic)]
 pub enum Error {
@@ -33,16 +46,134 @@ pub enum Error {
 	#[error(transparent)]
 	#[diagnostic(transparent)]
 	TemplateNotFound(TemplateNotFoundError),
+
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	Manifest(#[from] ManifestError),
+
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	Condition(#[from] ConditionError),
+
+	#[error("File name '{filename}' renders to '{rendered}', which escapes its directory")]
+	#[diagnostic(code(template_error::PathEscapes))]
+	PathEscapes {
+		filename: String,
+		rendered: String,
+		#[help]
+		advice: String,
+	},
+
+	#[error(
+		"Refusing to write '{}' outside of the output directory '{}'",
+		path.to_string_lossy(),
+		root.to_string_lossy()
+	)]
+	#[diagnostic(code(template_error::OutsideOutput))]
+	OutsideOutput {
+		path: PathBuf,
+		root: PathBuf,
+		#[help]
+		advice: String,
+	},
+
+	#[error("'{name}' is a category of templates, not a template")]
+	#[diagnostic(
+		code(template_error::TemplateIsCategory),
+		help("Generate one of its templates: {templates}")
+	)]
+	TemplateIsCategory { name: String, templates: String },
+
+	#[error("Namespace '{namespace}' is not configured")]
+	#[diagnostic(
+		code(template_error::NamespaceNotFound),
+		help("Configured namespaces: {available}")
+	)]
+	NamespaceNotFound {
+		namespace: String,
+		available: String,
+	},
+
+	#[error("Templates compose each other in a cycle: {0}")]
+	#[diagnostic(code(template_error::CompositionCycle))]
+	CompositionCycle(String),
+
+	#[error("Templates extend each other in a cycle: {0}")]
+	#[diagnostic(code(template_error::ExtensionCycle))]
+	ExtensionCycle(String),
+
+	#[error(
+		"File '{}' is rendered by both template '{first}' and '{second}'",
+		path.to_string_lossy()
+	)]
+	#[diagnostic(
+		code(template_error::DuplicateOutput),
+		help("Rename the file in one of the templates, or give one of them a condition.")
+	)]
+	DuplicateOutput {
+		path: PathBuf,
+		first: String,
+		second: String,
+	},
+
+	#[error("Injection target '{}' does not exist", .0.to_string_lossy())]
+	#[diagnostic(
+		code(template_error::InjectTargetNotFound),
+		help("Injection targets are relative to the output directory.")
+	)]
+	InjectTargetNotFound(PathBuf),
+
+	#[error("Anchor '{anchor}' was not found in '{}'", target.to_string_lossy())]
+	#[diagnostic(code(template_error::InjectAnchorNotFound))]
+	InjectAnchorNotFound { anchor: String, target: PathBuf },
+
+	#[error("Injection into '{}' at position '{position}' has no anchor", target.to_string_lossy())]
+	#[diagnostic(
+		code(template_error::InjectAnchorMissing),
+		help("Set `anchor` or `pattern`, or use the `append` or `prepend` positions.")
+	)]
+	InjectAnchorMissing { target: PathBuf, position: Position },
+
+	#[error("Injection pattern '{0}' is not a valid regex")]
+	#[diagnostic(code(template_error::InjectPatternInvalid))]
+	InjectPatternInvalid(String),
+
+	#[error("Found {} errors in templates", errors.len())]
+	#[diagnostic(
+		code(template_error::Many),
+		help("Every error was found in a single run, fix them all before the next one.")
+	)]
+	Many {
+		#[related]
+		errors: Vec<Error>,
+	},
 }
 
 impl Error {
-	pub fn template_not_found(name: String, templates_path: PathBuf) -> Self {
+	pub fn template_not_found(name: String, templates_paths: Vec<PathBuf>) -> Self {
 		Self::TemplateNotFound(TemplateNotFoundError {
-			templates_dir: templates_path,
+			templates_dirs: templates_paths,
 			template: Box::from(name),
 		})
 	}
 
+	/// Fails with every error of `errors`, nested bundles of errors are flattened
+	pub fn collect(errors: Vec<Error>) -> Result<(), Self> {
+		let mut flattened = vec![];
+		for error in errors {
+			match error {
+				Self::Many { errors } => flattened.extend(errors),
+				error => flattened.push(error),
+			}
+		}
+
+		match flattened.len() {
+			0 => Ok(()),
+			1 => Err(flattened.remove(0)),
+			_ => Err(Self::Many { errors: flattened }),
+		}
+	}
+
 	pub fn template_invalid(template_name: String, templates_dir: PathBuf) -> Self {
 		Self::TemplateNotValid {
 			template_name,
@@ -52,44 +183,82 @@ impl Error {
 }
 
 #[derive(Debug, thiserror::Error, Diagnostic)]
-#[error("Template '{template}' was not found in {}.", templates_dir.to_string_lossy())]
+#[error("Template '{template}' was not found in {}.", self.dirs())]
 #[diagnostic(
     code(TemplateNotFoundError),
     help("You might refer to one of theese templates.\nAvailable templates: {}", self.templates())
 )]
 pub struct TemplateNotFoundError {
 	template: Box<str>,
-	templates_dir: PathBuf,
+	templates_dirs: Vec<PathBuf>,
 }
 
 impl TemplateNotFoundError {
-	pub fn templates<'a>(&'a self) -> TemplateDisplay<'a> {
-		TemplateDisplay(&self.templates_dir)
+	pub fn templates(&self) -> TemplateDisplay<'_> {
+		TemplateDisplay(&self.templates_dirs)
+	}
+
+	fn dirs(&self) -> String {
+		self
+			.templates_dirs
+			.iter()
+			.map(|dir| dir.to_string_lossy())
+			.collect::<Vec<_>>()
+			.join(", ")
 	}
 }
 
-pub struct TemplateDisplay<'a>(&'a std::path::Path);
+/// Lists the templates of every templates directory, marking the ones shadowed by a template with
+/// the same name in an earlier directory
+pub struct TemplateDisplay<'a>(&'a [PathBuf]);
 
 impl<'a> Display for TemplateDisplay<'a> {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		let read_dir = match std::fs::read_dir(self.0) {
-			Ok(read_dir) => read_dir,
-			Err(e) => return write!(f, "Could not read directory, {}", e),
-		};
-
-		writeln!(f)?;
+		let mut seen = vec![];
 
-		for entry in read_dir {
-			let entry = match entry {
-				Ok(entry) => entry,
-				Err(_) => {
-					let _ = f.write_str("<Could not read entry>\n");
+		for dir in self.0 {
+			let read_dir = match std::fs::read_dir(dir) {
+				Ok(read_dir) => read_dir,
+				Err(e) => {
+					writeln!(f, "\nCould not read {}, {}", dir.to_string_lossy(), e)?;
 					continue;
 				}
 			};
 
-			let filename = entry.file_name();
-			writeln!(f, "- {}", filename.to_string_lossy())?;
+			writeln!(f, "\nIn {}:", dir.to_string_lossy())?;
+
+			for entry in read_dir {
+				let entry = match entry {
+					Ok(entry) => entry,
+					Err(_) => {
+						let _ = f.write_str("<Could not read entry>\n");
+						continue;
+					}
+				};
+
+				let filename = entry.file_name();
+				if filename == PARTIALS_DIR {
+					continue;
+				}
+
+				let path = entry.path();
+				let names = match is_category(&path) {
+					true => category_templates(&path)
+						.into_iter()
+						.map(|template| format!("{}/{template}", filename.to_string_lossy()))
+						.collect(),
+					false => vec![filename.to_string_lossy().into_owned()],
+				};
+
+				for name in names {
+					if seen.contains(&name) {
+						writeln!(f, "- {name} (shadowed)")?;
+					} else {
+						writeln!(f, "- {name}")?;
+						seen.push(name);
+					}
+				}
+			}
 		}
 
 		Ok(())
@@ -112,6 +281,55 @@ pub enum TemplateError {
 		span: (usize, usize),
 	},
 
+	#[error("{reason}")]
+	#[diagnostic(
+		code(BlockInvalid),
+		help("Blocks look like {{(#block name)}}content{{(/block)}}.")
+	)]
+	BlockInvalid {
+		reason: &'static str,
+		#[source_code]
+		src: miette::NamedSource,
+		#[label("here")]
+		span: (usize, usize),
+	},
+
+	#[error("partial '{partial}' not found")]
+	#[diagnostic(
+		code(PartialNotFound),
+		help("Partials are files of the _partials directory, in the templates directory.")
+	)]
+	PartialNotFound {
+		partial: String,
+		#[source_code]
+		src: miette::NamedSource,
+		#[label("included here")]
+		span: (usize, usize),
+	},
+
+	#[error("partials include each other in a cycle: {chain}")]
+	#[diagnostic(code(IncludeCycle))]
+	IncludeCycle {
+		chain: String,
+		#[source_code]
+		src: miette::NamedSource,
+		#[label("included here")]
+		span: (usize, usize),
+	},
+
+	#[error("partial '{partial}' is invalid")]
+	#[diagnostic(code(PartialInvalid))]
+	PartialInvalid {
+		partial: String,
+		#[source_code]
+		src: miette::NamedSource,
+		#[label("included here")]
+		span: (usize, usize),
+		/// Every error inside of the partial
+		#[related]
+		error: Vec<TemplateError>,
+	},
+
 	#[error("pipe '{pipe}' not found")]
 	#[diagnostic(code(PipeNotFound), help("This pipe is not available."))]
 	PipeNotFound {
@@ -124,23 +342,77 @@ pub enum TemplateError {
 }
 
 impl Error {
+	pub fn from_block_error(error: BlockError, content: String, filename: &Path) -> Self {
+		Self::Template(TemplateError::BlockInvalid {
+			reason: error.reason,
+			src: miette::NamedSource::new(filename.to_string_lossy(), content),
+			span: (error.span.start, error.span.len()),
+		})
+	}
+
 	pub fn from_parse_error(error: parse_error::Error, content: String, filename: String) -> Self {
 		match error {
 			parse_error::Error::External(_) => Self::CouldNotRead(PathBuf::from(filename)),
-			parse_error::Error::Internal(parse_error::InternalError::PipeNotFound(PipeUndefined {
-				slice,
-				pipename,
-			})) => Self::Template(TemplateError::PipeNotFound {
-				pipe: pipename,
-				src: miette::NamedSource::new(filename, content),
-				span: slice,
-			}),
-			parse_error::Error::Internal(parse_error::InternalError::ParamNotFound(param)) => {
-				Self::Template(TemplateError::VariableNotFound {
-					src: miette::NamedSource::new(filename, content),
-					span: (param.start, param.end - param.start),
-				})
+			parse_error::Error::Internal(error) => {
+				let errors = TemplateError::from_internal(error, content, filename);
+				let errors = errors.into_iter().map(Self::Template).collect();
+				match Self::collect(errors) {
+					Err(error) => error,
+					Ok(()) => unreachable!("Internal errors hold at least one error"),
+				}
 			}
 		}
 	}
 }
+
+impl TemplateError {
+	/// Every error of `error`, many when it is a bundle of them
+	fn from_internal(
+		error: parse_error::InternalError,
+		content: String,
+		filename: String,
+	) -> Vec<Self> {
+		let src = miette::NamedSource::new(&filename, content.clone());
+
+		let error = match error {
+			parse_error::InternalError::Many(errors) => {
+				return errors
+					.into_iter()
+					.flat_map(|error| Self::from_internal(error, content.clone(), filename.clone()))
+					.collect();
+			}
+			parse_error::InternalError::PipeNotFound(PipeUndefined { slice, pipename }) => {
+				Self::PipeNotFound {
+					pipe: pipename,
+					src,
+					span: slice,
+				}
+			}
+			parse_error::InternalError::ParamNotFound(param) => Self::VariableNotFound {
+				src,
+				span: (param.start, param.end - param.start),
+			},
+			parse_error::InternalError::PartialNotFound(partial) => Self::PartialNotFound {
+				partial: partial.name,
+				src,
+				span: (partial.start, partial.end - partial.start),
+			},
+			parse_error::InternalError::IncludeCycle(cycle) => Self::IncludeCycle {
+				chain: cycle.chain,
+				src,
+				span: (cycle.start, cycle.end - cycle.start),
+			},
+			parse_error::InternalError::PartialInvalid(partial) => {
+				let filename = format!("{PARTIALS_DIR}/{}", partial.name);
+				Self::PartialInvalid {
+					error: Self::from_internal(*partial.error, partial.content, filename),
+					partial: partial.name,
+					src,
+					span: (partial.start, partial.end - partial.start),
+				}
+			}
+		};
+
+		vec![error]
+	}
+}
@@ -0,0 +1,134 @@
+use std::{
+	collections::HashMap,
+	io,
+	path::{Component, Path, PathBuf},
+};
+
+use super::Error;
+
+/// Refuses writes that resolve outside of the output root, like through a variable set to
+/// `../../etc` or a symlink, unless they are explicitly allowed
+#[derive(Debug)]
+pub struct OutputGuard {
+	root: PathBuf,
+	allowed: bool,
+	variables: HashMap<String, String>,
+}
+
+impl OutputGuard {
+	pub fn new(
+		root: &Path,
+		allowed: bool,
+		variables: &HashMap<String, String>,
+	) -> Result<Self, Error> {
+		let root = resolve(root).map_err(|_| Error::CouldNotRead(root.to_path_buf()))?;
+
+		Ok(Self {
+			root,
+			allowed,
+			variables: variables.clone(),
+		})
+	}
+
+	/// Checks `path` is inside the output root once symlinks and `..` are resolved, it must be
+	/// called before creating or writing `path`
+	pub fn check(&self, path: &Path) -> Result<(), Error> {
+		if self.allowed {
+			return Ok(());
+		}
+
+		let resolved = resolve(path).map_err(|_| Error::CouldNotRead(path.to_path_buf()))?;
+		if resolved.starts_with(&self.root) {
+			return Ok(());
+		}
+
+		Err(Error::OutsideOutput {
+			path: path.to_path_buf(),
+			root: self.root.clone(),
+			advice: escape_advice(&path.to_string_lossy(), &self.variables),
+		})
+	}
+
+	/// Checks every path before any of them is written, so an escape does not leave half an
+	/// output behind
+	pub fn check_all<'a>(&self, paths: impl IntoIterator<Item = &'a Path>) -> Result<(), Error> {
+		paths.into_iter().try_for_each(|path| self.check(path))
+	}
+}
+
+/// Explains which variable made `rendered` escape its directory, if any
+pub fn escape_advice(rendered: &str, variables: &HashMap<String, String>) -> String {
+	let mut variables = variables
+		.iter()
+		.filter(|(_, value)| is_escaping(value) && rendered.contains(value.as_str()))
+		.collect::<Vec<_>>();
+	variables.sort();
+
+	let mut advice = String::new();
+	for (name, value) in variables {
+		advice.push_str(&format!("Variable '{name}' is '{value}'. "));
+	}
+
+	advice.push_str("Pass --allow-outside-output to write outside of the output directory anyway.");
+	advice
+}
+
+fn is_escaping(value: &str) -> bool {
+	Path::new(value)
+		.components()
+		.any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// Canonicalizes the longest existing ancestor of `path`, then appends the rest of it resolving
+/// `..` by hand, since the rest does not exist yet
+fn resolve(path: &Path) -> io::Result<PathBuf> {
+	let path = if path.is_absolute() {
+		path.to_path_buf()
+	} else {
+		std::env::current_dir()?.join(path)
+	};
+
+	let mut existing = path.as_path();
+	let mut missing = vec![];
+	while !existing.exists() {
+		match (existing.parent(), existing.file_name()) {
+			(Some(parent), Some(name)) => {
+				missing.push(name);
+				existing = parent;
+			}
+			// Only `..` can end here, canonicalizing will take care of it
+			_ => break,
+		}
+	}
+
+	let mut resolved = existing.canonicalize()?;
+	for name in missing.into_iter().rev() {
+		resolved.push(name);
+	}
+
+	Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_refuse_paths_outside_of_the_root() {
+		let variables = HashMap::from([("path".to_owned(), "../../etc".to_owned())]);
+		let guard = OutputGuard::new(Path::new("./src/commands"), false, &variables).unwrap();
+
+		assert!(guard.check(Path::new("./src/commands/new/file.rs")).is_ok());
+		let Err(Error::OutsideOutput { advice, .. }) =
+			guard.check(Path::new("./src/commands/../../etc/file"))
+		else {
+			panic!("Path outside of the root was allowed");
+		};
+		assert!(advice.starts_with("Variable 'path' is '../../etc'."));
+
+		let guard = OutputGuard::new(Path::new("./src/commands"), true, &variables).unwrap();
+		assert!(guard
+			.check(Path::new("./src/commands/../../etc/file"))
+			.is_ok());
+	}
+}
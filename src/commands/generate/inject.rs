@@ -0,0 +1,255 @@
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
+
+use regex::Regex;
+
+use crate::template::{
+	manifest::{Injection, Position, WriteMode, MANIFEST_FILENAME},
+	parse::TemplateParse,
+};
+
+use super::{error::Error, render, Rendered};
+
+enum Anchor {
+	Marker(String),
+	Pattern(Regex),
+}
+
+impl Anchor {
+	fn from_injection(injection: &Injection) -> Result<Option<Self>, Error> {
+		if let Some(marker) = &injection.anchor {
+			return Ok(Some(Anchor::Marker(marker.clone())));
+		}
+
+		match &injection.pattern {
+			Some(pattern) => match Regex::new(pattern) {
+				Ok(regex) => Ok(Some(Anchor::Pattern(regex))),
+				Err(_) => Err(Error::InjectPatternInvalid(pattern.clone())),
+			},
+			None => Ok(None),
+		}
+	}
+
+	fn matches(&self, line: &str) -> bool {
+		match self {
+			Anchor::Marker(marker) => line.contains(marker.as_str()),
+			Anchor::Pattern(regex) => regex.is_match(line),
+		}
+	}
+
+	fn as_str(&self) -> &str {
+		match self {
+			Anchor::Marker(marker) => marker,
+			Anchor::Pattern(regex) => regex.as_str(),
+		}
+	}
+}
+
+/// The content of an injection target with the snippet in it, not written yet
+#[derive(Debug)]
+pub struct Injected {
+	pub target: PathBuf,
+	pub content: String,
+}
+
+/// Renders the snippet described by `injection` into the content of its target, resolved inside
+/// `output`. The target is read from `injected`, then from `rendered`, then from disk, so
+/// injections see the content generated before them. Returns `None` if the snippet is already
+/// in the target, so running the same injection twice does not duplicate it.
+pub fn render_injection<T: TemplateParse>(
+	injection: &Injection,
+	output: &Path,
+	template_parser: &T,
+	rendered: &[Rendered],
+	injected: &[Injected],
+) -> Result<Option<Injected>, Error> {
+	let target = output.join(render(
+		template_parser,
+		&injection.target,
+		MANIFEST_FILENAME,
+	)?);
+	let mut snippet = render(template_parser, &injection.content, MANIFEST_FILENAME)?;
+	if !snippet.ends_with('\n') {
+		snippet.push('\n');
+	}
+
+	let position = injection.position();
+	let anchor = Anchor::from_injection(injection)?;
+	if anchor.is_none() && matches!(position, Position::Before | Position::After) {
+		return Err(Error::InjectAnchorMissing { target, position });
+	}
+
+	let content = match target_content(&target, rendered, injected) {
+		Some(content) => content,
+		None => return Err(Error::InjectTargetNotFound(target)),
+	};
+
+	if contains_lines(&content, &snippet) {
+		return Ok(None);
+	}
+
+	match insert_snippet(&content, &snippet, anchor.as_ref(), position) {
+		Some(content) => Ok(Some(Injected { target, content })),
+		None => {
+			let anchor = anchor.map(|anchor| anchor.as_str().to_owned());
+			Err(Error::InjectAnchorNotFound {
+				anchor: anchor.unwrap_or_default(),
+				target,
+			})
+		}
+	}
+}
+
+/// Content `target` will have once `rendered` and `injected` are written
+fn target_content(target: &Path, rendered: &[Rendered], injected: &[Injected]) -> Option<String> {
+	if let Some(injected) = injected
+		.iter()
+		.rev()
+		.find(|injected| injected.target == target)
+	{
+		return Some(injected.content.clone());
+	}
+
+	let file = rendered.iter().rev().find_map(|entry| match entry {
+		Rendered::File {
+			path,
+			content,
+			mode,
+		} if path == target => Some((String::from_utf8_lossy(content), *mode)),
+		_ => None,
+	});
+	let existing = fs::read_to_string(target).ok();
+
+	match (file, existing) {
+		(Some((content, WriteMode::Append)), Some(existing)) => Some(existing + &content),
+		(Some((content, _)), _) => Some(content.into_owned()),
+		(None, existing) => existing,
+	}
+}
+
+/// Writes every injected target, unless `dry_run`
+pub fn write_injected(injected: Vec<Injected>, dry_run: bool) -> Result<Vec<PathBuf>, Error> {
+	let mut targets = vec![];
+
+	for Injected { target, content } in injected {
+		if !dry_run && fs::write(&target, content).is_err() {
+			return Err(Error::CouldNotWrite(target));
+		}
+		// Many injections into the same target write it many times, it is reported once
+		if !targets.contains(&target) {
+			targets.push(target);
+		}
+	}
+
+	Ok(targets)
+}
+
+/// Whether the lines of `snippet` follow each other in `content`, ignoring indentation, so
+/// `mod a;` is not found in `pub mod a;`
+fn contains_lines(content: &str, snippet: &str) -> bool {
+	let content = content.lines().map(str::trim).collect::<Vec<_>>();
+	let snippet = snippet.lines().map(str::trim).collect::<Vec<_>>();
+
+	snippet.is_empty() || content.windows(snippet.len()).any(|lines| lines == snippet)
+}
+
+/// Returns `None` if `position` is relative to an anchor that is not in `content`
+fn insert_snippet(
+	content: &str,
+	snippet: &str,
+	anchor: Option<&Anchor>,
+	position: Position,
+) -> Option<String> {
+	let mut injected = String::with_capacity(content.len() + snippet.len() + 1);
+
+	match (position, anchor) {
+		(Position::Prepend, _) => {
+			injected.push_str(snippet);
+			injected.push_str(content);
+		}
+		(Position::Append, _) => {
+			injected.push_str(content);
+			if !content.is_empty() && !content.ends_with('\n') {
+				injected.push('\n');
+			}
+			injected.push_str(snippet);
+		}
+		(Position::Before | Position::After, Some(anchor)) => {
+			let mut found = false;
+
+			for line in content.split_inclusive('\n') {
+				if found || !anchor.matches(line) {
+					injected.push_str(line);
+					continue;
+				}
+
+				found = true;
+				if position == Position::Before {
+					injected.push_str(snippet);
+					injected.push_str(line);
+				} else {
+					injected.push_str(line);
+					if !line.ends_with('\n') {
+						injected.push('\n');
+					}
+					injected.push_str(snippet);
+				}
+			}
+
+			if !found {
+				return None;
+			}
+		}
+		(Position::Before | Position::After, None) => return None,
+	}
+
+	Some(injected)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_insert_around_anchor() {
+		let content = "mod a;\n// modules\nfn main() {}";
+		let anchor = Anchor::Marker("// modules".into());
+
+		let after = insert_snippet(content, "mod b;\n", Some(&anchor), Position::After).unwrap();
+		assert_eq!(after, "mod a;\n// modules\nmod b;\nfn main() {}");
+
+		let before = insert_snippet(content, "mod b;\n", Some(&anchor), Position::Before).unwrap();
+		assert_eq!(before, "mod a;\nmod b;\n// modules\nfn main() {}");
+	}
+
+	#[test]
+	fn should_append_on_a_new_line() {
+		let appended = insert_snippet(
+			"export * from './a';",
+			"export * from './b';\n",
+			None,
+			Position::Append,
+		);
+		assert_eq!(
+			appended.unwrap(),
+			"export * from './a';\nexport * from './b';\n"
+		);
+	}
+
+	#[test]
+	fn should_find_whole_lines_only() {
+		let content = "pub mod a;\n// mod b;\n  mod c;\n";
+		assert!(!contains_lines(content, "mod a;\n"));
+		assert!(!contains_lines(content, "mod b;\n"));
+		assert!(contains_lines(content, "mod c;\n"));
+		assert!(!contains_lines(content, "mod c;\nmod d;\n"));
+	}
+
+	#[test]
+	fn should_fail_on_missing_anchor() {
+		let anchor = Anchor::Pattern(Regex::new("^routes").unwrap());
+		assert!(insert_snippet("const x = 1;\n", "y\n", Some(&anchor), Position::After).is_none());
+	}
+}
@@ -0,0 +1,231 @@
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	config::toml_config::TomlConfig,
+	template::manifest::{Manifest, MANIFEST_FILENAME},
+};
+
+use super::Error;
+
+/// Separates the names of templates generated together, like `component,test,story`
+pub const TEMPLATES_SEPARATOR: char = ',';
+
+/// Separates the namespace of a template from its name, like `team:react/component`
+pub const NAMESPACE_SEPARATOR: char = ':';
+
+/// Directories where templates are looked up
+#[derive(Debug, Default, Clone)]
+pub struct TemplateRoots {
+	/// Looked up in order for names without a namespace
+	pub paths: Vec<PathBuf>,
+	/// Selected by the namespace of a name
+	pub namespaces: HashMap<String, PathBuf>,
+}
+
+impl TemplateRoots {
+	pub fn from_config(config: &TomlConfig) -> Self {
+		Self {
+			paths: config.templates_paths.clone(),
+			namespaces: config.namespaces.clone(),
+		}
+	}
+
+	/// Every directory, the namespaced ones after the rest and sorted by namespace
+	pub fn all(&self) -> Vec<PathBuf> {
+		let mut namespaces = self.namespaces.iter().collect::<Vec<_>>();
+		namespaces.sort();
+
+		let mut all = self.paths.clone();
+		for (_, path) in namespaces {
+			if !all.contains(path) {
+				all.push(path.clone());
+			}
+		}
+
+		all
+	}
+
+	/// Splits a reference like `team:react/component` into the directories to look it up in and
+	/// the name of the template under them
+	fn candidates<'a>(&self, reference: &'a str) -> Result<(Vec<PathBuf>, &'a str), Error> {
+		match reference.split_once(NAMESPACE_SEPARATOR) {
+			Some((namespace, name)) => match self.namespaces.get(namespace) {
+				Some(path) => Ok((vec![path.clone()], name)),
+				None => {
+					let mut available = self.namespaces.keys().cloned().collect::<Vec<_>>();
+					available.sort();
+					Err(Error::NamespaceNotFound {
+						namespace: namespace.to_owned(),
+						available: available.join(", "),
+					})
+				}
+			},
+			None => Ok((self.paths.clone(), reference)),
+		}
+	}
+}
+
+/// A template found in one of the templates directories
+#[derive(Debug)]
+pub struct Template {
+	pub name: String,
+	/// Manifest of the template, merged with the manifests of the templates it extends
+	pub manifest: Manifest,
+	/// Directories of the templates this one extends, from the base, followed by its own
+	pub layers: Vec<PathBuf>,
+}
+
+impl Template {
+	/// Looks `name` up in every templates directory, the first one that has it shadows the rest.
+	/// Names with a namespace are only looked up in the directory of the namespace.
+	pub fn resolve(name: String, roots: &TemplateRoots) -> Result<Self, Error> {
+		Self::resolve_extending(name, roots, &mut vec![])
+	}
+
+	fn resolve_extending(
+		name: String,
+		roots: &TemplateRoots,
+		extending: &mut Vec<String>,
+	) -> Result<Self, Error> {
+		let (templates_paths, relative_name) = roots.candidates(&name)?;
+		let found = templates_paths
+			.iter()
+			.map(|templates_path| templates_path.join(relative_name))
+			.find_map(|path| path.metadata().ok().map(|metadata| (path, metadata)));
+		// Use a match to avoid borrow checker issues
+		let (path, metadata) = match found {
+			Some(found) => found,
+			None => return Err(Error::template_not_found(name, templates_paths)),
+		};
+
+		if !metadata.file_type().is_dir() {
+			return Err(Error::template_invalid(name, path));
+		}
+
+		if is_category(&path) {
+			return Err(Error::TemplateIsCategory {
+				templates: category_templates(&path).join(", "),
+				name,
+			});
+		}
+
+		let mut manifest = Manifest::from_template_dir(&path)?;
+		let mut layers = vec![];
+
+		if let Some(base_name) = manifest.extends.take() {
+			extending.push(name.clone());
+			if extending.contains(&base_name) {
+				extending.push(base_name);
+				return Err(Error::ExtensionCycle(extending.join(" -> ")));
+			}
+
+			let base = Self::resolve_extending(base_name, roots, extending)?;
+			extending.pop();
+
+			manifest = base.manifest.extended_by(manifest);
+			layers = base.layers;
+		}
+
+		layers.push(path);
+		Ok(Self {
+			name,
+			manifest,
+			layers,
+		})
+	}
+
+	/// Resolves every template of a comma separated `reference`, followed by the templates they
+	/// compose. Each template is resolved once even if it is composed many times.
+	pub fn resolve_all(reference: &str, roots: &TemplateRoots) -> Result<Vec<Self>, Error> {
+		let mut templates = vec![];
+		let mut composing = vec![];
+
+		for name in reference.split(TEMPLATES_SEPARATOR).map(str::trim) {
+			Self::resolve_composed(name, roots, &mut composing, &mut templates)?;
+		}
+
+		Ok(templates)
+	}
+
+	fn resolve_composed(
+		name: &str,
+		roots: &TemplateRoots,
+		composing: &mut Vec<String>,
+		templates: &mut Vec<Self>,
+	) -> Result<(), Error> {
+		if composing.iter().any(|composer| composer == name) {
+			composing.push(name.to_owned());
+			return Err(Error::CompositionCycle(composing.join(" -> ")));
+		}
+
+		if templates.iter().any(|template| template.name == name) {
+			return Ok(());
+		}
+
+		let template = Self::resolve(name.to_owned(), roots)?;
+		let composed = template.manifest.compose.clone();
+		templates.push(template);
+
+		composing.push(name.to_owned());
+		for name in composed.iter() {
+			Self::resolve_composed(name, roots, composing, templates)?;
+		}
+		composing.pop();
+
+		Ok(())
+	}
+}
+
+/// Categories are directories without a manifest that organize templates, like `react` holding
+/// `react/component`. They are told apart from templates without a manifest by holding, at any
+/// depth, a directory with a manifest.
+pub fn is_category(dir: &Path) -> bool {
+	if dir.join(MANIFEST_FILENAME).exists() {
+		return false;
+	}
+
+	sub_dirs(dir)
+		.iter()
+		.any(|sub_dir| sub_dir.join(MANIFEST_FILENAME).exists() || is_category(sub_dir))
+}
+
+/// Names of the templates of a category, relative to it, like `component` or `hooks/state`
+pub fn category_templates(dir: &Path) -> Vec<String> {
+	let mut templates = vec![];
+	for sub_dir in sub_dirs(dir) {
+		let name = match sub_dir.file_name() {
+			Some(name) => name.to_string_lossy().into_owned(),
+			None => continue,
+		};
+
+		if is_category(&sub_dir) {
+			let nested = category_templates(&sub_dir);
+			templates.extend(
+				nested
+					.into_iter()
+					.map(|template| format!("{name}/{template}")),
+			);
+		} else {
+			templates.push(name);
+		}
+	}
+
+	templates.sort();
+	templates
+}
+
+fn sub_dirs(dir: &Path) -> Vec<PathBuf> {
+	let read_dir = match dir.read_dir() {
+		Ok(read_dir) => read_dir,
+		Err(_) => return vec![],
+	};
+
+	read_dir
+		.flatten()
+		.filter(|entry| entry.file_type().is_ok_and(|filetype| filetype.is_dir()))
+		.map(|entry| entry.path())
+		.collect()
+}
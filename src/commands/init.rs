@@ -0,0 +1,118 @@
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
+
+use crate::config::toml_config::CONFIG_FILENAME;
+
+/// Name of the example template created in the templates directory
+pub const EXAMPLE_TEMPLATE: &str = "example";
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum InitError {
+	#[error("Config '{}' already exists", .0.to_string_lossy())]
+	#[diagnostic(code(init::ConfigExists), help("Pass --force to overwrite it."))]
+	ConfigExists(PathBuf),
+
+	#[error("Could not find the user config directory")]
+	#[diagnostic(code(init::UserDirNotFound), help("Set $XDG_CONFIG_HOME or $HOME."))]
+	UserDirNotFound,
+
+	#[error("Could not write '{}'", .0.to_string_lossy())]
+	CouldNotWrite(PathBuf),
+}
+
+#[derive(Debug, Default)]
+pub struct Initialized {
+	pub config: PathBuf,
+	pub templates_path: PathBuf,
+	/// The example template, when the templates directory did not have one yet
+	pub example: Option<PathBuf>,
+}
+
+/// Writes a commented config into `dir`, and creates its `templates_path`, relative to `dir`,
+/// with an example template. An existing config is only overwritten with `force`.
+pub fn init(dir: &Path, templates_path: &str, force: bool) -> Result<Initialized, InitError> {
+	let config = dir.join(CONFIG_FILENAME);
+	if config.exists() && !force {
+		return Err(InitError::ConfigExists(config));
+	}
+
+	fs::create_dir_all(dir).map_err(|_| InitError::CouldNotWrite(dir.to_path_buf()))?;
+	fs::write(&config, config_content(templates_path))
+		.map_err(|_| InitError::CouldNotWrite(config.clone()))?;
+
+	let templates_path = dir.join(templates_path);
+	let example_dir = templates_path.join(EXAMPLE_TEMPLATE);
+	let mut initialized = Initialized {
+		config,
+		templates_path,
+		example: None,
+	};
+
+	// Keep the templates of the team, even when overwriting the config
+	if example_dir.exists() {
+		return Ok(initialized);
+	}
+
+	fs::create_dir_all(&example_dir).map_err(|_| InitError::CouldNotWrite(example_dir.clone()))?;
+	let example = example_dir.join("{(name)}.md");
+	fs::write(&example, EXAMPLE_CONTENT).map_err(|_| InitError::CouldNotWrite(example.clone()))?;
+	initialized.example = Some(example_dir);
+
+	Ok(initialized)
+}
+
+const EXAMPLE_CONTENT: &str = "# {(name|capitalize_once)}
+
+Generated with `cco generate example {(name)}`, edit this template or add new ones next to it.
+";
+
+fn config_content(templates_path: &str) -> String {
+	format!(
+		r#"# Config files are read from the most global to the most local directory, later ones override
+# the settings of earlier ones. `cco config sources` prints where each setting comes from.
+
+# Directories where templates are looked up, relative to this file. Templates of the first
+# directories shadow the ones with the same name in the next ones.
+templates_path = {templates_path:?}
+
+# Templates directories only used for templates with their prefix, like `team:react/component`
+# [namespaces]
+# team = "~/team-templates"
+
+# Variables of every template, `-p name=value` overrides them
+[variables]
+# author = "Jane Doe"
+
+# Settings layered over the rest of the file when selected with `--profile work`
+# [profiles.work.variables]
+# author = "Jane Doe, ACME"
+"#
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::toml_config::TomlConfig;
+
+	#[test]
+	fn should_write_a_readable_config() {
+		let dir = Path::new("./files/init");
+		let _ = fs::remove_dir_all(dir);
+
+		let initialized = init(dir, "templates", false).unwrap();
+		assert_eq!(initialized.example, Some(dir.join("templates/example")));
+
+		let config = TomlConfig::from_files(&[initialized.config], None, []).unwrap();
+		assert_eq!(config.templates_paths, vec![dir.join("templates")]);
+
+		assert!(matches!(
+			init(dir, "templates", false),
+			Err(InitError::ConfigExists(_))
+		));
+		let initialized = init(dir, "templates", true).unwrap();
+		assert_eq!(initialized.example, None);
+	}
+}
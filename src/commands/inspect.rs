@@ -0,0 +1,335 @@
+use std::{
+	collections::{BTreeMap, HashMap},
+	fmt::Display,
+	fs,
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	commands::generate::{layered_entries, layered_source, Error, Template, TemplateRoots, NAME_VAR},
+	config::{toml_config::Source, Config},
+	template::{
+		manifest::{injection_strings, MANIFEST_FILENAME},
+		parse::{DefaultTemplateParse, ParamsBrowser, INCLUDE, SEPARATOR},
+	},
+};
+
+/// Where a variable or a pipe is used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Usage {
+	/// Path of the file, relative to its templates directory
+	pub path: PathBuf,
+	/// Line and column in the content of the file, `None` in its name or its condition
+	pub position: Option<(usize, usize)>,
+}
+
+impl Display for Usage {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.position {
+			Some((line, column)) => write!(f, "{}:{line}:{column}", self.path.to_string_lossy()),
+			None => write!(f, "{} (file name)", self.path.to_string_lossy()),
+		}
+	}
+}
+
+/// Variables and pipes used by a template, in the names, conditions and contents of its files and
+/// of the partials they include
+#[derive(Debug, Default)]
+pub struct Inspection {
+	pub variables: BTreeMap<String, Vec<Usage>>,
+	pub pipes: BTreeMap<String, Vec<Usage>>,
+	/// Partials that are included but not found
+	pub partials_not_found: BTreeMap<String, Vec<Usage>>,
+}
+
+/// Walks every file of the templates of `reference`, and of the templates they compose
+pub fn inspect(reference: &str, config: &Config) -> Result<Inspection, Error> {
+	let roots = TemplateRoots::from_config(&config.toml_config);
+	let parser = DefaultTemplateParse::with_vars(HashMap::new()).with_partials(&roots.all());
+	let mut inspector = Inspector {
+		parser: &parser,
+		inspection: Inspection::default(),
+		included: vec![],
+	};
+
+	for template in Template::resolve_all(reference, &roots)? {
+		inspector.inspect_dir(&template, Path::new(""))?;
+		inspector.inspect_manifests(&template);
+	}
+
+	Ok(inspector.inspection)
+}
+
+struct Inspector<'a> {
+	parser: &'a DefaultTemplateParse,
+	inspection: Inspection,
+	/// Partials already walked, each one is walked once
+	included: Vec<String>,
+}
+
+impl<'a> Inspector<'a> {
+	fn inspect_dir(&mut self, template: &Template, relative_dir: &Path) -> Result<(), Error> {
+		for entry in layered_entries(&template.layers, relative_dir)? {
+			if entry.filename == MANIFEST_FILENAME {
+				continue;
+			}
+
+			let relative_path = relative_dir.join(&entry.filename);
+			let path = Path::new(&template.name).join(&relative_path);
+			let filename = entry.filename.to_string_lossy();
+			for param in ParamsBrowser::new(&filename) {
+				let usage = Usage {
+					path: path.clone(),
+					position: None,
+				};
+				self.record(param.name, usage);
+			}
+
+			// Conditions are written in the file name, or in the `files` rules of the manifest
+			let settings = template
+				.manifest
+				.entry_settings(&relative_path, &filename)?;
+			if let Some(condition) = settings.condition {
+				let usage = Usage {
+					path: path.clone(),
+					position: None,
+				};
+				let usages = self
+					.inspection
+					.variables
+					.entry(condition.variable().to_owned());
+				usages.or_default().push(usage);
+			}
+
+			if entry.filetype.is_dir() {
+				self.inspect_dir(template, &relative_path)?;
+			} else {
+				let (content, _) = layered_source(&entry.sources)?;
+				self.inspect_content(&content, &path);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Walks the injections of the manifest of every layer of `template`, they are rendered too
+	fn inspect_manifests(&mut self, template: &Template) {
+		let path = Path::new(&template.name).join(MANIFEST_FILENAME);
+
+		for layer in template.layers.iter() {
+			// Unreadable manifests fail to resolve the template
+			let Ok(manifest) = fs::read_to_string(layer.join(MANIFEST_FILENAME)) else {
+				continue;
+			};
+
+			for string in injection_strings(&manifest) {
+				let mut offset = string.offset;
+				for line in string.value.split_inclusive('\n') {
+					for param in ParamsBrowser::new(line) {
+						// Offsets in the value are not offsets in the manifest
+						let at = match string.verbatim {
+							true => offset + param.start,
+							false => string.offset,
+						};
+						let usage = Usage {
+							path: path.clone(),
+							position: Some(line_column(&manifest, at)),
+						};
+						self.record(param.name, usage);
+					}
+					offset += line.len();
+				}
+			}
+		}
+	}
+
+	fn inspect_content(&mut self, content: &str, path: &Path) {
+		// Expressions do not span lines, templates are rendered line by line
+		for (i, line) in content.lines().enumerate() {
+			for param in ParamsBrowser::new(line) {
+				let usage = Usage {
+					path: path.to_path_buf(),
+					position: Some((i + 1, param.start + 1)),
+				};
+				self.record(param.name, usage);
+			}
+		}
+	}
+
+	fn record(&mut self, expression: &str, usage: Usage) {
+		if let Some(partial) = expression.strip_prefix(INCLUDE) {
+			return self.include(partial.trim(), usage);
+		}
+
+		let mut slices = expression.split(SEPARATOR);
+		// Split always returns a first slice
+		let variable = slices.next().unwrap();
+		for pipe in slices {
+			let usages = self.inspection.pipes.entry(pipe.to_owned()).or_default();
+			usages.push(usage.clone());
+		}
+
+		let usages = self.inspection.variables.entry(variable.to_owned());
+		usages.or_default().push(usage);
+	}
+
+	fn include(&mut self, partial: &str, usage: Usage) {
+		let Some((path, content)) = self.parser.read_partial(partial) else {
+			let usages = self.inspection.partials_not_found.entry(partial.to_owned());
+			usages.or_default().push(usage);
+			return;
+		};
+
+		if self.included.iter().any(|included| included == partial) {
+			return;
+		}
+		self.included.push(partial.to_owned());
+
+		let path = path
+			.parent()
+			.and_then(Path::file_name)
+			.map(|partials| Path::new(partials).join(partial))
+			.unwrap_or(path);
+		self.inspect_content(&content, &path);
+	}
+}
+
+/// Line and column of `offset` in `content`, both starting at 1
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+	let before = &content[..offset];
+	let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+
+	(before.matches('\n').count() + 1, offset - line_start + 1)
+}
+
+/// Prints each variable with the value the config gives it, or as missing, and each pipe, then
+/// where they are used
+pub fn print_inspection(template: &str, inspection: &Inspection, config: &Config) {
+	let toml_config = &config.toml_config;
+	let parser = DefaultTemplateParse::with_vars(HashMap::new());
+
+	println!(
+		"Template '{template}' uses {} variables:",
+		inspection.variables.len()
+	);
+	for (variable, usages) in inspection.variables.iter() {
+		let status = match toml_config.variables.get(variable) {
+			Some(value) => {
+				let source = toml_config.sources.variables.get(variable);
+				format!(
+					"= {value:?} ({}) ✓",
+					source.map_or("default".to_owned(), Source::to_string)
+				)
+			}
+			None if variable == NAME_VAR => "set by the output name ✓".to_owned(),
+			None => "missing, pass it with -p ✗".to_owned(),
+		};
+		println!("{variable} {status}");
+		print_usages(usages);
+	}
+
+	if !inspection.pipes.is_empty() {
+		println!();
+		println!("Uses {} pipes:", inspection.pipes.len());
+	}
+	for (pipe, usages) in inspection.pipes.iter() {
+		match parser.has_pipe(pipe) {
+			true => println!("{pipe} ✓"),
+			false => println!("{pipe} unknown ✗"),
+		}
+		print_usages(usages);
+	}
+
+	if !inspection.partials_not_found.is_empty() {
+		println!();
+		println!("Includes partials that were not found:");
+	}
+	for (partial, usages) in inspection.partials_not_found.iter() {
+		println!("{partial} ✗");
+		print_usages(usages);
+	}
+}
+
+fn print_usages(usages: &[Usage]) {
+	for usage in usages {
+		println!("  {usage}");
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use super::*;
+	use crate::config::toml_config::TomlConfig;
+
+	#[test]
+	fn should_collect_variables_and_pipes() {
+		let templates_path = PathBuf::from("./files/inspect/templates");
+		fs::create_dir_all(templates_path.join("component")).unwrap();
+		fs::create_dir_all(templates_path.join("_partials")).unwrap();
+		fs::write(
+			templates_path.join("component/{(name|kebab_case)}.ts"),
+			"// {(author)}\n{(> header)}\nexport const {(name)} = 1;",
+		)
+		.unwrap();
+		fs::write(
+			templates_path.join("component/{(name)}.test.ts[if with_tests]"),
+			"",
+		)
+		.unwrap();
+		fs::write(templates_path.join("component/{(name)}.css"), "").unwrap();
+		fs::write(
+			templates_path.join("component").join(MANIFEST_FILENAME),
+			"[[inject]]\ntarget = \"index.ts\"\ncontent = \"export * from './{(name|kebab_case)}';\"\n\n[files.\"{(name)}.css\"]\nwhen = \"style == css\"",
+		)
+		.unwrap();
+		fs::write(
+			templates_path.join("_partials/header"),
+			"// {(license|capitalize_once)}",
+		)
+		.unwrap();
+
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![templates_path],
+				..Default::default()
+			},
+		};
+		let inspection = inspect("component", &config).unwrap();
+
+		let variables = inspection.variables.keys().collect::<Vec<_>>();
+		assert_eq!(
+			variables,
+			vec!["author", "license", "name", "style", "with_tests"]
+		);
+		let pipes = inspection.pipes.keys().collect::<Vec<_>>();
+		assert_eq!(pipes, vec!["capitalize_once", "kebab_case"]);
+
+		let usages = &inspection.variables["name"];
+		assert_eq!(
+			usages[0].to_string(),
+			"component/{(name|kebab_case)}.ts (file name)"
+		);
+		assert_eq!(
+			usages[1].to_string(),
+			"component/{(name|kebab_case)}.ts:3:14"
+		);
+		assert_eq!(
+			inspection.variables["license"][0].to_string(),
+			"_partials/header:1:4"
+		);
+		assert_eq!(
+			inspection.pipes["kebab_case"][1].to_string(),
+			"component/.template.toml:3:29"
+		);
+		assert_eq!(
+			inspection.variables["with_tests"][0].to_string(),
+			"component/{(name)}.test.ts[if with_tests] (file name)"
+		);
+		assert_eq!(
+			inspection.variables["style"][0].to_string(),
+			"component/{(name)}.css (file name)"
+		);
+	}
+}
@@ -0,0 +1,541 @@
+use std::{
+	collections::{HashMap, HashSet},
+	fs,
+	path::{Path, PathBuf},
+};
+
+use miette::NamedSource;
+
+use crate::{
+	commands::generate::{
+		self, category_templates, layered_entries, layered_source, Template, TemplateRoots,
+		NAMESPACE_SEPARATOR, NAME_VAR, TEMPLATES_SEPARATOR,
+	},
+	config::{
+		toml_config::{key_span, parse_file, suggest},
+		Config,
+	},
+	template::{
+		condition::Condition,
+		manifest::{
+			injection_strings, CONDITION_START, FILE_RULE_KEYS, INJECTION_KEYS, MANIFEST_FILENAME,
+			MANIFEST_KEYS,
+		},
+		parse::{
+			DefaultTemplateParse, ParamsBrowser, TemplateParam, INCLUDE, LEFT, PARTIALS_DIR, SEPARATOR,
+		},
+	},
+};
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum LintProblem {
+	#[error("Pipe '{pipe}' does not exist")]
+	#[diagnostic(code(lint::UnknownPipe))]
+	UnknownPipe {
+		pipe: String,
+		#[source_code]
+		src: NamedSource,
+		#[label("unknown pipe")]
+		span: (usize, usize),
+		#[help]
+		advice: String,
+	},
+
+	#[error("Tag is never closed")]
+	#[diagnostic(
+		code(lint::UnterminatedTag),
+		help("Tags look like {{(name)}} and can not span many lines.")
+	)]
+	UnterminatedTag {
+		#[source_code]
+		src: NamedSource,
+		#[label("opened here")]
+		span: (usize, usize),
+	},
+
+	#[error("Variable '{variable}' is not provided by any config")]
+	#[diagnostic(
+		code(lint::VariableNotProvided),
+		help("Set it in the [variables] of a config file, or pass it with -p {variable}=<value>.")
+	)]
+	VariableNotProvided {
+		variable: String,
+		#[source_code]
+		src: NamedSource,
+		#[label("used here")]
+		span: (usize, usize),
+	},
+
+	#[error("Partial '{partial}' was not found")]
+	#[diagnostic(
+		code(lint::PartialNotFound),
+		help("Partials are files of the _partials directory, in the templates directory.")
+	)]
+	PartialNotFound {
+		partial: String,
+		#[source_code]
+		src: NamedSource,
+		#[label("included here")]
+		span: (usize, usize),
+	},
+
+	#[error("Condition '{condition}' can never hold")]
+	#[diagnostic(
+		code(lint::UnreachableBranch),
+		help("It contradicts the condition '{enclosing}' of an enclosing directory.")
+	)]
+	UnreachableBranch {
+		condition: Condition,
+		enclosing: Condition,
+		#[source_code]
+		src: NamedSource,
+		#[label("never generated")]
+		span: (usize, usize),
+	},
+
+	#[error("Unknown key '{key}' in template manifest")]
+	#[diagnostic(code(lint::UnknownManifestKey))]
+	UnknownManifestKey {
+		key: String,
+		#[source_code]
+		src: NamedSource,
+		#[label("unknown key")]
+		span: Option<(usize, usize)>,
+		#[help]
+		advice: String,
+	},
+
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	Template(#[from] generate::Error),
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+#[error("Found {} problems in templates", problems.len())]
+#[diagnostic(code(lint::LintFailed))]
+pub struct LintFailed {
+	#[related]
+	pub problems: Vec<LintProblem>,
+}
+
+/// Checks the templates of `reference`, or every template, without generating them. Returns how
+/// many templates were checked.
+pub fn lint(reference: Option<&str>, config: &Config) -> Result<usize, LintFailed> {
+	let roots = TemplateRoots::from_config(&config.toml_config);
+	let parser = DefaultTemplateParse::with_vars(HashMap::new()).with_partials(&roots.all());
+	let variables = provided_variables(config);
+	let mut linter = Linter {
+		parser: &parser,
+		variables: &variables,
+		linted: HashSet::new(),
+		problems: vec![],
+	};
+
+	let names = match reference {
+		Some(reference) => reference
+			.split(TEMPLATES_SEPARATOR)
+			.map(|name| name.trim().to_owned())
+			.collect(),
+		None => template_names(&roots),
+	};
+
+	for name in names.iter() {
+		match Template::resolve(name.clone(), &roots) {
+			Ok(template) => linter.lint_template(&template),
+			Err(error) => linter.problems.push(error.into()),
+		}
+	}
+
+	match linter.problems.is_empty() {
+		true => Ok(names.len()),
+		false => Err(LintFailed {
+			problems: linter.problems,
+		}),
+	}
+}
+
+/// Variables of the effective config, and of every config file and profile, since any of them
+/// may be the one used to generate
+fn provided_variables(config: &Config) -> HashSet<String> {
+	let mut variables = config
+		.toml_config
+		.variables
+		.keys()
+		.cloned()
+		.collect::<HashSet<_>>();
+
+	for file in config.toml_config.sources.files.iter() {
+		// Invalid config files are reported by `config --validate`
+		let Ok(content) = fs::read_to_string(file) else {
+			continue;
+		};
+		let Ok(partial) = parse_file(file, &content) else {
+			continue;
+		};
+
+		let profiles = partial
+			.profiles
+			.iter()
+			.flat_map(|profiles| profiles.values());
+		for layer in std::iter::once(&partial).chain(profiles) {
+			variables.extend(layer.variables.iter().flat_map(|vars| vars.keys().cloned()));
+		}
+	}
+
+	variables
+}
+
+/// Every template of every templates directory, without the shadowed ones
+fn template_names(roots: &TemplateRoots) -> Vec<String> {
+	let mut names = vec![];
+	for templates_path in roots.paths.iter() {
+		names.extend(category_templates(templates_path));
+	}
+
+	let mut namespaces = roots.namespaces.iter().collect::<Vec<_>>();
+	namespaces.sort();
+	for (namespace, templates_path) in namespaces {
+		let templates = category_templates(templates_path).into_iter();
+		names.extend(templates.map(|name| format!("{namespace}{NAMESPACE_SEPARATOR}{name}")));
+	}
+
+	let mut seen = HashSet::new();
+	names.retain(|name| {
+		let template = name.rsplit(NAMESPACE_SEPARATOR).next().unwrap_or(name);
+		template != PARTIALS_DIR && !template.starts_with('.') && seen.insert(name.clone())
+	});
+	names
+}
+
+struct Linter<'a> {
+	parser: &'a DefaultTemplateParse,
+	variables: &'a HashSet<String>,
+	/// Files, manifests and partials already checked, templates share them when they extend
+	/// each other
+	linted: HashSet<PathBuf>,
+	problems: Vec<LintProblem>,
+}
+
+impl<'a> Linter<'a> {
+	fn lint_template(&mut self, template: &Template) {
+		for layer in template.layers.iter() {
+			self.lint_manifest(&layer.join(MANIFEST_FILENAME));
+		}
+
+		if let Err(error) = self.lint_dir(template, Path::new(""), &mut vec![]) {
+			self.problems.push(error.into());
+		}
+	}
+
+	/// Arguments:
+	/// * `conditions`: conditions of the directories enclosing `relative_dir`
+	fn lint_dir(
+		&mut self,
+		template: &Template,
+		relative_dir: &Path,
+		conditions: &mut Vec<Condition>,
+	) -> Result<(), generate::Error> {
+		let mut entries = layered_entries(&template.layers, relative_dir)?;
+		entries.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+		for entry in entries {
+			let filename = entry.filename.to_string_lossy();
+			if filename == MANIFEST_FILENAME {
+				continue;
+			}
+
+			let relative_path = relative_dir.join(filename.as_ref());
+			// Sources are never empty, each entry is found in at least one layer
+			let path = entry.sources[entry.sources.len() - 1].clone();
+			let is_new = self.linted.insert(path.clone());
+			let settings = match template.manifest.entry_settings(&relative_path, &filename) {
+				Ok(settings) => settings,
+				Err(error) => {
+					self.problems.push(generate::Error::from(error).into());
+					continue;
+				}
+			};
+
+			if is_new {
+				let src = || NamedSource::new(path.to_string_lossy(), filename.to_string());
+				let enclosing = conditions.iter().find(|enclosing| {
+					let condition = settings.condition.as_ref();
+					condition.is_some_and(|condition| condition.contradicts(enclosing))
+				});
+
+				// Conditions of the manifest are not part of the file name
+				let condition_start = filename.rfind(CONDITION_START).unwrap_or(0);
+				let condition_span = (condition_start, filename.len() - condition_start);
+				if let (Some(condition), Some(enclosing)) = (&settings.condition, enclosing) {
+					self.problems.push(LintProblem::UnreachableBranch {
+						condition: condition.clone(),
+						enclosing: enclosing.clone(),
+						src: src(),
+						span: condition_span,
+					});
+				}
+
+				if let Some(condition) = &settings.condition {
+					let variable = condition.variable();
+					if !self.is_provided(variable) {
+						self.problems.push(LintProblem::VariableNotProvided {
+							variable: variable.to_owned(),
+							src: src(),
+							span: condition_span,
+						});
+					}
+				}
+
+				self.lint_content(settings.filename, 0, &src);
+			}
+
+			if entry.filetype.is_dir() {
+				let condition = settings.condition.clone();
+				conditions.extend(condition.clone());
+				self.lint_dir(template, &relative_path, conditions)?;
+				if condition.is_some() {
+					conditions.pop();
+				}
+			} else if is_new {
+				let content = match layered_source(&entry.sources) {
+					Ok((content, _)) => content,
+					Err(error) => {
+						self.problems.push(error.into());
+						continue;
+					}
+				};
+				let src = || NamedSource::new(path.to_string_lossy(), content.clone());
+				self.lint_content(&content, 0, &src);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Checks every tag of `content`, written at `start` of the named source `src`
+	fn lint_content(&mut self, content: &str, start: usize, src: &dyn Fn() -> NamedSource) {
+		let mut offset = start;
+		// Tags do not span lines, templates are rendered line by line
+		for line in content.split_inclusive('\n') {
+			let params = ParamsBrowser::new(line).collect::<Vec<_>>();
+
+			for (start, _) in line.match_indices(LEFT) {
+				let is_closed = params
+					.iter()
+					.any(|param| param.start <= start && start < param.end);
+				if !is_closed {
+					self.problems.push(LintProblem::UnterminatedTag {
+						src: src(),
+						span: (offset + start, line.trim_end().len() - start),
+					});
+				}
+			}
+
+			for param in params.iter() {
+				self.lint_tag(param, offset, src);
+			}
+
+			offset += line.len();
+		}
+	}
+
+	fn lint_tag(&mut self, param: &TemplateParam, offset: usize, src: &dyn Fn() -> NamedSource) {
+		let start = offset + param.start + LEFT.len();
+
+		if let Some(partial) = param.name.strip_prefix(INCLUDE) {
+			let partial = partial.trim();
+			let Some((partial_path, content)) = self.parser.read_partial(partial) else {
+				self.problems.push(LintProblem::PartialNotFound {
+					partial: partial.to_owned(),
+					src: src(),
+					span: (start, param.name.len()),
+				});
+				return;
+			};
+
+			if self.linted.insert(partial_path.clone()) {
+				let src = || NamedSource::new(partial_path.to_string_lossy(), content.clone());
+				self.lint_content(&content, 0, &src);
+			}
+			return;
+		}
+
+		let mut slices = param.name.split(SEPARATOR);
+		// Split always returns a first slice
+		let variable = slices.next().unwrap();
+		if !self.is_provided(variable) {
+			self.problems.push(LintProblem::VariableNotProvided {
+				variable: variable.to_owned(),
+				src: src(),
+				span: (start, variable.len()),
+			});
+		}
+
+		let mut pipe_start = start + variable.len();
+		for pipe in slices {
+			pipe_start += SEPARATOR.len_utf8();
+			if !self.parser.has_pipe(pipe) {
+				let known = self.parser.pipes();
+				let advice = match suggest(pipe, &known) {
+					Some(known) => format!("Did you mean `{known}`?"),
+					None => format!("Available pipes: {}", known.join(", ")),
+				};
+				self.problems.push(LintProblem::UnknownPipe {
+					pipe: pipe.to_owned(),
+					src: src(),
+					span: (pipe_start, pipe.len()),
+					advice,
+				});
+			}
+			pipe_start += pipe.len();
+		}
+	}
+
+	fn is_provided(&self, variable: &str) -> bool {
+		variable == NAME_VAR || self.variables.contains(variable)
+	}
+
+	fn lint_manifest(&mut self, path: &Path) {
+		if !self.linted.insert(path.to_path_buf()) {
+			return;
+		}
+
+		// Unreadable and unparseable manifests fail to resolve the template
+		let Ok(content) = fs::read_to_string(path) else {
+			return;
+		};
+		let Ok(table) = content.parse::<toml::Table>() else {
+			return;
+		};
+
+		let mut unknown = vec![];
+		for (key, value) in table.iter() {
+			match key.as_str() {
+				"files" => {
+					for (file, rule) in value.as_table().into_iter().flatten() {
+						let keys = rule.as_table().into_iter().flatten().map(|(key, _)| key);
+						let table = format!("files.{file:?}");
+						unknown.extend(
+							keys
+								.filter(|key| !FILE_RULE_KEYS.contains(&key.as_str()))
+								.map(|key| (Some(table.clone()), key.clone(), &FILE_RULE_KEYS[..])),
+						);
+					}
+				}
+				"inject" => {
+					let injections = value.as_array().into_iter().flatten();
+					for injection in injections.filter_map(toml::Value::as_table) {
+						unknown.extend(
+							injection
+								.keys()
+								.filter(|key| !INJECTION_KEYS.contains(&key.as_str()))
+								.map(|key| {
+									(
+										Some("[inject]".to_owned()),
+										key.clone(),
+										&INJECTION_KEYS[..],
+									)
+								}),
+						);
+					}
+				}
+				key if !MANIFEST_KEYS.contains(&key) => {
+					unknown.push((None, key.to_owned(), &MANIFEST_KEYS[..]));
+				}
+				_ => {}
+			}
+		}
+
+		for (table, key, known) in unknown {
+			let advice = match suggest(&key, known) {
+				Some(known) => format!("Did you mean `{known}`?"),
+				None => format!("Known keys: {}", known.join(", ")),
+			};
+
+			self.problems.push(LintProblem::UnknownManifestKey {
+				span: key_span(&content, table.as_deref(), &key),
+				src: NamedSource::new(path.to_string_lossy(), content.clone()),
+				advice,
+				key,
+			});
+		}
+
+		for string in injection_strings(&content) {
+			let src = || match string.verbatim {
+				true => NamedSource::new(path.to_string_lossy(), content.clone()),
+				// Offsets in the value are not offsets in the manifest
+				false => NamedSource::new(path.to_string_lossy(), string.value.clone()),
+			};
+			let start = if string.verbatim { string.offset } else { 0 };
+			self.lint_content(&string.value, start, &src);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::toml_config::{ConfigSources, TomlConfig};
+
+	#[test]
+	fn should_report_every_problem() {
+		let templates_path = PathBuf::from("./files/lint/templates");
+		let template = templates_path.join("component");
+		fs::create_dir_all(template.join("tests[if with_tests]")).unwrap();
+		fs::write(
+			template.join(MANIFEST_FILENAME),
+			"compose = []
+extend = \"base\"
+[[inject]]
+target = \"{(modfile)}\"
+content = \"mod {(name|nope)};\"\n",
+		)
+		.unwrap();
+		fs::write(
+			template.join("{(name|kebab_cas)}.ts"),
+			"// {(author)} {(license)}\nexport const {(name = 1;",
+		)
+		.unwrap();
+		fs::write(
+			template.join("tests[if with_tests]/{(name)}.spec.ts[if !with_tests]"),
+			"",
+		)
+		.unwrap();
+
+		// Variables of profiles that are not selected are provided too
+		let config_file = PathBuf::from("./files/lint/config.toml");
+		fs::write(
+			&config_file,
+			"[profiles.work.variables]\nlicense = \"MIT\"\n",
+		)
+		.unwrap();
+
+		let config = Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![templates_path],
+				variables: HashMap::from([("author".to_owned(), "me".to_owned())]),
+				sources: ConfigSources {
+					files: vec![config_file],
+					..Default::default()
+				},
+				..Default::default()
+			},
+		};
+		let problems = lint(None, &config).unwrap_err().problems;
+
+		let problems = problems.iter().map(ToString::to_string).collect::<Vec<_>>();
+		assert_eq!(
+			problems,
+			vec![
+				"Unknown key 'extend' in template manifest",
+				"Variable 'modfile' is not provided by any config",
+				"Pipe 'nope' does not exist",
+				"Variable 'with_tests' is not provided by any config",
+				"Condition '!with_tests' can never hold",
+				"Variable 'with_tests' is not provided by any config",
+				"Pipe 'kebab_cas' does not exist",
+				"Tag is never closed",
+			]
+		);
+	}
+}
@@ -1,14 +1,21 @@
 use crate::{
+	commands::generate::{is_category, TemplateRoots, NAMESPACE_SEPARATOR},
 	config::Config,
 	dir_browser::{
 		browser::DirBrowser,
 		entry::{Entry, Symlink},
 	},
+	template::parse::PARTIALS_DIR,
+};
+use std::{
+	io::{self, stdout, IsTerminal, Stdout, Write},
+	path::Path,
 };
-use std::io::{self, stdout, IsTerminal, Stdout, Write};
 
+/// Lists the templates of every templates directory in order, the templates shadowed by one with
+/// the same name in an earlier directory are marked. Namespaced directories are listed after the
+/// rest, grouped by namespace. Categories of templates are rendered as trees.
 pub fn list(config: &Config) -> io::Result<()> {
-	let browser = DirBrowser::new(config.toml_config.templates_path.clone())?;
 
     let mut stdout = stdout();
     let fun = if stdout.is_terminal() {
@@ -27,5 +34,98 @@ pub fn list(config: &Config) -> io::Result<()> {
 		}
     };
 
-    browser.into_iter().map(|entry| fun(&mut stdout, entry)).collect()
+	let roots = TemplateRoots::from_config(&config.toml_config);
+	let mut seen = vec![];
+	for templates_path in roots.paths.iter() {
+		list_dir(&mut stdout, templates_path, "", &mut seen, fun)?;
+	}
+
+	let mut namespaces = roots.namespaces.iter().collect::<Vec<_>>();
+	namespaces.sort();
+	for (namespace, templates_path) in namespaces {
+		writeln!(stdout, "{namespace}{NAMESPACE_SEPARATOR}")?;
+		list_dir(&mut stdout, templates_path, "  ", &mut vec![], fun)?;
+	}
+
+	Ok(())
+}
+
+/// Prints the templates of `templates_path`, marking the ones already `seen` in an earlier
+/// directory as shadowed
+fn list_dir(
+	stdout: &mut Stdout,
+	templates_path: &Path,
+	indent: &str,
+	seen: &mut Vec<String>,
+	print: fn(&mut Stdout, Entry) -> io::Result<()>,
+) -> io::Result<()> {
+	let browser = match DirBrowser::new(templates_path.to_path_buf()) {
+		Ok(browser) => browser,
+		// A personal or team directory may not exist in every machine
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+		Err(e) => return Err(e),
+	};
+
+	let entries = browser
+		.into_iter()
+		.filter(|entry| !matches!(entry, Entry::Directory(name) if name == PARTIALS_DIR));
+	for entry in entries {
+		let name = entry_name(&entry).to_owned();
+		write!(stdout, "{indent}")?;
+		if seen.contains(&name) {
+			write!(stdout, "(shadowed) ")?;
+		}
+
+		let is_dir = matches!(entry, Entry::Directory(_));
+		print(stdout, entry)?;
+
+		let path = templates_path.join(&name);
+		if is_dir && is_category(&path) {
+			list_category(stdout, &path, indent, print)?;
+		}
+		seen.push(name);
+	}
+
+	Ok(())
+}
+
+/// Prints the templates and nested categories of a category as a tree
+fn list_category(
+	stdout: &mut Stdout,
+	dir: &Path,
+	prefix: &str,
+	print: fn(&mut Stdout, Entry) -> io::Result<()>,
+) -> io::Result<()> {
+	let mut names = dir
+		.read_dir()?
+		.flatten()
+		.filter(|entry| entry.file_type().is_ok_and(|filetype| filetype.is_dir()))
+		.filter_map(|entry| entry.file_name().into_string().ok())
+		.collect::<Vec<_>>();
+	names.sort();
+
+	for (i, name) in names.iter().enumerate() {
+		let (branch, continuation) = match i == names.len() - 1 {
+			true => ("└── ", "    "),
+			false => ("├── ", "│   "),
+		};
+
+		write!(stdout, "{prefix}{branch}")?;
+		print(stdout, Entry::Directory(name.clone()))?;
+
+		let path = dir.join(name);
+		if is_category(&path) {
+			list_category(stdout, &path, &format!("{prefix}{continuation}"), print)?;
+		}
+	}
+
+	Ok(())
+}
+
+fn entry_name(entry: &Entry) -> &str {
+	match entry {
+		Entry::File(filename) => filename,
+		Entry::Directory(name) => name,
+		Entry::Symlink(Symlink { name, .. }) => name,
+	}
 }
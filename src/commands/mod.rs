@@ -1,3 +1,10 @@
+pub mod config;
+pub mod generate;
+pub mod init;
+pub mod inspect;
+pub mod lint;
 pub mod list;
+pub mod new_template;
 pub mod path;
-pub mod generate;
+pub mod undo;
+pub mod update;
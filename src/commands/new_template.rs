@@ -0,0 +1,272 @@
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	commands::generate::NAME_VAR,
+	config::Config,
+	template::parse::{CASE_PIPES, LEFT, PARTIALS_DIR},
+};
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum NewTemplateError {
+	#[error("Template '{}' already exists", .0.to_string_lossy())]
+	#[diagnostic(
+		code(new_template::TemplateExists),
+		help("Choose another name, or remove the template first.")
+	)]
+	TemplateExists(PathBuf),
+
+	#[error("Template name '{0}' is invalid")]
+	#[diagnostic(
+		code(new_template::TemplateNameInvalid),
+		help("Template names are relative paths, like `component` or `react/component`.")
+	)]
+	TemplateNameInvalid(String),
+
+	#[error("Template tags are already written in '{path}'")]
+	#[diagnostic(
+		code(new_template::TagInSource),
+		help("The template would take it for a tag, remove it from the source and add it back to the template by hand.")
+	)]
+	TagInSource {
+		path: String,
+		#[source_code]
+		src: miette::NamedSource,
+		#[label("here")]
+		span: (usize, usize),
+	},
+
+	#[error("Error while reading file '{}'", .0.to_string_lossy())]
+	CouldNotRead(PathBuf),
+	#[error("Error while writing file '{}'", .0.to_string_lossy())]
+	CouldNotWrite(PathBuf),
+}
+
+#[derive(Debug)]
+pub struct Scaffolded {
+	pub template_dir: PathBuf,
+	/// Files of the template, with the sample name replaced
+	pub files: Vec<PathBuf>,
+	/// Binary sources, like images, templates can only be rendered out of text
+	pub skipped: Vec<PathBuf>,
+}
+
+/// Creates `template` in the first templates directory out of the files and directories of
+/// `from`. Every occurrence of `sample`, in file names and contents, is replaced by the `name`
+/// variable, piped to the case style it was written in.
+///
+/// A sample of many words, like `IconButton`, tells every case style apart, while `button` is
+/// the same in camel, kebab and snake case.
+pub fn new_template(
+	template: &str,
+	from: &[PathBuf],
+	sample: &str,
+	config: &Config,
+) -> Result<Scaffolded, NewTemplateError> {
+	let is_valid = Path::new(template)
+		.components()
+		.all(|component| matches!(component, std::path::Component::Normal(_)));
+	let templates_path = config.toml_config.templates_paths.first();
+	let template_dir = match templates_path {
+		Some(templates_path) if is_valid && template != PARTIALS_DIR => templates_path.join(template),
+		_ => return Err(NewTemplateError::TemplateNameInvalid(template.to_owned())),
+	};
+
+	if template_dir.exists() {
+		return Err(NewTemplateError::TemplateExists(template_dir));
+	}
+
+	let mut scaffolded = Scaffolded {
+		template_dir: template_dir.clone(),
+		files: vec![],
+		skipped: vec![],
+	};
+	let variants = variants(sample);
+	let copied = from
+		.iter()
+		.try_for_each(|path| copy(path, &template_dir, &variants, &mut scaffolded));
+	if let Err(error) = copied {
+		// Half a template would not render what the sources are
+		let _ = fs::remove_dir_all(&template_dir);
+		return Err(error);
+	}
+
+	Ok(scaffolded)
+}
+
+/// Copies `path` into `dir`, a directory with all of its entries
+fn copy(
+	path: &Path,
+	dir: &Path,
+	variants: &[(String, String)],
+	scaffolded: &mut Scaffolded,
+) -> Result<(), NewTemplateError> {
+	let filename = path
+		.file_name()
+		.ok_or_else(|| NewTemplateError::CouldNotRead(path.to_path_buf()))?;
+	let filename = filename.to_string_lossy();
+	check_tags(&path.to_string_lossy(), &filename)?;
+	let target = dir.join(templatize(&filename, variants));
+
+	if path.is_dir() {
+		let read_dir =
+			fs::read_dir(path).map_err(|_| NewTemplateError::CouldNotRead(path.to_path_buf()))?;
+		fs::create_dir_all(&target).map_err(|_| NewTemplateError::CouldNotWrite(target.clone()))?;
+
+		let mut entries = read_dir
+			.flatten()
+			.map(|entry| entry.path())
+			.collect::<Vec<_>>();
+		entries.sort();
+		for entry in entries {
+			copy(&entry, &target, variants, scaffolded)?;
+		}
+		return Ok(());
+	}
+
+	let content = fs::read(path).map_err(|_| NewTemplateError::CouldNotRead(path.to_path_buf()))?;
+	let content = match String::from_utf8(content) {
+		Ok(content) => content,
+		// Generation could never render binary files, like images
+		Err(_) => {
+			scaffolded.skipped.push(path.to_path_buf());
+			return Ok(());
+		}
+	};
+	check_tags(&path.to_string_lossy(), &content)?;
+	let content = templatize(&content, variants);
+
+	fs::create_dir_all(dir).map_err(|_| NewTemplateError::CouldNotWrite(dir.to_path_buf()))?;
+	fs::write(&target, content).map_err(|_| NewTemplateError::CouldNotWrite(target.clone()))?;
+	scaffolded.files.push(target);
+
+	Ok(())
+}
+
+/// Text of the sources that looks like a tag, like `onClick={(event) => go(event)}` in JSX, would
+/// break the template, there is no way to escape it
+fn check_tags(path: &str, content: &str) -> Result<(), NewTemplateError> {
+	match content.find(LEFT) {
+		Some(start) => Err(NewTemplateError::TagInSource {
+			path: path.to_owned(),
+			src: miette::NamedSource::new(path, content.to_owned()),
+			span: (start, LEFT.len()),
+		}),
+		None => Ok(()),
+	}
+}
+
+/// Each way `sample` can be written, with the expression replacing it, the longest first so
+/// `ICON_BUTTON` is not taken for `icon` followed by something else. The sample as written is
+/// replaced by the variable itself, and of case styles writing it the same, the first one wins.
+fn variants(sample: &str) -> Vec<(String, String)> {
+	let mut variants = vec![(sample.to_owned(), format!("{{({NAME_VAR})}}"))];
+
+	for (pipe_name, pipe) in CASE_PIPES {
+		let written = pipe(sample);
+		if variants.iter().all(|(variant, _)| *variant != written) {
+			variants.push((written, format!("{{({NAME_VAR}|{pipe_name})}}")));
+		}
+	}
+
+	// Empty variants would match everywhere
+	variants.retain(|(variant, _)| !variant.is_empty());
+	variants.sort_by_key(|(variant, _)| std::cmp::Reverse(variant.len()));
+	variants
+}
+
+fn templatize(content: &str, variants: &[(String, String)]) -> String {
+	let mut templatized = String::with_capacity(content.len());
+	let mut rest = content;
+
+	while let Some(c) = rest.chars().next() {
+		let found = variants
+			.iter()
+			.find(|(variant, _)| rest.starts_with(variant.as_str()));
+
+		match found {
+			Some((variant, expression)) => {
+				templatized.push_str(expression);
+				rest = &rest[variant.len()..];
+			}
+			None => {
+				templatized.push(c);
+				rest = &rest[c.len_utf8()..];
+			}
+		}
+	}
+
+	templatized
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_replace_every_case_style() {
+		let variants = variants("IconButton");
+		let content = "export const IconButton = () => <button className=\"icon-button\" />;\nconst ICON_BUTTON_SIZE = iconButtonSize;";
+
+		assert_eq!(
+			templatize(content, &variants),
+			"export const {(name)} = () => <button className=\"{(name|kebab_case)}\" />;\nconst {(name|constant_case)}_SIZE = {(name|camel_case)}Size;"
+		);
+	}
+
+	#[test]
+	fn should_refuse_sources_with_tags() {
+		let dir = PathBuf::from("./files/new_template_tags");
+		let _ = fs::remove_dir_all(&dir);
+		let source = dir.join("source");
+		fs::create_dir_all(&source).unwrap();
+		fs::write(source.join("README.md"), "# Button\n").unwrap();
+		fs::write(
+			source.join("Button.tsx"),
+			"export const Button = () => <button onClick={(event) => go(event)} />;\n",
+		)
+		.unwrap();
+
+		let config = Config {
+			toml_config: crate::config::toml_config::TomlConfig {
+				templates_paths: vec![dir.join("templates")],
+				..Default::default()
+			},
+		};
+		let scaffolded = new_template("button", &[source], "Button", &config);
+		assert!(matches!(
+			scaffolded,
+			Err(NewTemplateError::TagInSource { span: (44, 2), .. })
+		));
+		assert!(!dir.join("templates/button").exists());
+	}
+
+	#[test]
+	fn should_skip_binary_files() {
+		let dir = PathBuf::from("./files/new_template_binary");
+		let _ = fs::remove_dir_all(&dir);
+		let source = dir.join("source");
+		fs::create_dir_all(&source).unwrap();
+		fs::write(source.join("Button.tsx"), "export const Button = null;\n").unwrap();
+		fs::write(
+			source.join("icon.png"),
+			[0x89, b'P', b'N', b'G', 0xff, 0xfe],
+		)
+		.unwrap();
+
+		let config = Config {
+			toml_config: crate::config::toml_config::TomlConfig {
+				templates_paths: vec![dir.join("templates")],
+				..Default::default()
+			},
+		};
+		let from = [source.clone()];
+		let scaffolded = new_template("button", &from, "Button", &config).unwrap();
+		let template = dir.join("templates/button/source");
+		assert_eq!(scaffolded.files, vec![template.join("{(name)}.tsx")]);
+		assert_eq!(scaffolded.skipped, vec![source.join("icon.png")]);
+		assert!(!template.join("icon.png").exists());
+	}
+}
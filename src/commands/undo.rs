@@ -0,0 +1,94 @@
+use std::{
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+use crate::history::{History, HistoryError, Run};
+
+#[derive(Debug, Default)]
+pub struct Undone {
+	pub removed: Vec<PathBuf>,
+	/// Generated files that were edited since the generation, they are kept
+	pub edited: Vec<PathBuf>,
+	/// Files that existed before the generation and have to be reverted by hand
+	pub modified: Vec<PathBuf>,
+	/// Directories created by the generation that are not empty anymore
+	pub kept_directories: Vec<PathBuf>,
+}
+
+/// Removes the files of the last run recorded in the history at `history_path`, as long as they
+/// are unmodified, and drops the run from the history.
+pub fn undo(history_path: &Path) -> Result<(Run, Undone), HistoryError> {
+	let mut history = History::read(history_path)?;
+	let run = history.runs.pop().ok_or(HistoryError::Empty)?;
+
+	let mut undone = Undone {
+		modified: run.modified.clone(),
+		..Default::default()
+	};
+
+	for file in run.files.iter() {
+		if !file.path.exists() {
+			continue;
+		}
+
+		if file.is_unmodified() && fs::remove_file(&file.path).is_ok() {
+			undone.removed.push(file.path.clone());
+		} else {
+			undone.edited.push(file.path.clone());
+		}
+	}
+
+	// Children were created after their parents
+	for dir in run.directories.iter().rev() {
+		match fs::remove_dir(dir) {
+			Ok(_) => {}
+			Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+			Err(_) => undone.kept_directories.push(dir.clone()),
+		}
+	}
+
+	history.write(history_path)?;
+
+	Ok((run, undone))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::history::FileRecord;
+
+	use super::*;
+
+	#[test]
+	fn should_keep_edited_files() {
+		let history_path = PathBuf::from("./files/undo/history.toml");
+		let dir = PathBuf::from("./files/undo/output/generated");
+		let _ = fs::remove_file(&history_path);
+		fs::create_dir_all(&dir).expect("Creating generated dir");
+
+		fs::write(dir.join("kept.txt"), "generated").unwrap();
+		fs::write(dir.join("removed.txt"), "generated").unwrap();
+		let run = Run {
+			template: "foo".into(),
+			variables: Default::default(),
+			output: dir.clone(),
+			files: vec![
+				FileRecord::read(&dir.join("kept.txt")).unwrap(),
+				FileRecord::read(&dir.join("removed.txt")).unwrap(),
+			],
+			directories: vec![dir.clone()],
+			modified: vec![],
+		};
+		History::record(&history_path, run).unwrap();
+		fs::write(dir.join("kept.txt"), "edited").unwrap();
+
+		let (_, undone) = undo(&history_path).unwrap();
+		assert_eq!(undone.removed.len(), 1);
+		assert_eq!(undone.edited.len(), 1);
+		assert_eq!(undone.kept_directories.len(), 1);
+		assert!(!dir.join("removed.txt").exists());
+		assert!(dir.join("kept.txt").exists());
+
+		assert!(matches!(undo(&history_path), Err(HistoryError::Empty)));
+	}
+}
@@ -0,0 +1,303 @@
+mod merge;
+
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	commands::generate::{
+		self, render_all, rendered_paths, OutputGuard, Rendered, Template, TemplateRoots,
+	},
+	config::Config,
+	history::{absolute, content_hash, FileRecord, History, HistoryError, Run},
+	template::{manifest::WriteMode, parse::DefaultTemplateParse},
+};
+
+use merge::merge;
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum UpdateError {
+	#[error("No generation of template '{0}' was recorded")]
+	#[diagnostic(
+		code(history::RunNotFound),
+		help("Only generations made from this directory can be updated.")
+	)]
+	RunNotFound(String),
+
+	#[error("No file of the generation of template '{0}' was recorded")]
+	#[diagnostic(
+		code(history::FilesNotFound),
+		help("Files of the generation may have been moved, generate the template again instead.")
+	)]
+	FilesNotFound(String),
+
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	History(#[from] HistoryError),
+
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	Generate(#[from] generate::Error),
+}
+
+#[derive(Debug, Default)]
+pub struct Updated {
+	pub template: String,
+	/// Files that took the template changes without conflicts
+	pub merged: Vec<PathBuf>,
+	/// Files the template added since the generation
+	pub created: Vec<PathBuf>,
+	/// Files with conflict markers to be solved by hand
+	pub conflicted: Vec<PathBuf>,
+	/// Generated files that are not part of the template anymore, they are kept
+	pub stale: Vec<PathBuf>,
+}
+
+/// Re-renders the last recorded generation, or the last one of `template_name`, with its
+/// original variables and merges the template changes into the current files.
+pub fn update(
+	template_name: Option<String>,
+	history_path: &Path,
+	config: Config,
+) -> Result<Updated, UpdateError> {
+	let mut history = History::read(history_path)?;
+	let index = history
+		.runs
+		.iter()
+		.rposition(|run| match &template_name {
+			Some(name) => &run.template == name,
+			None => true,
+		})
+		.ok_or_else(|| UpdateError::RunNotFound(template_name.unwrap_or_default()))?;
+
+	let run = &history.runs[index];
+	let roots = TemplateRoots::from_config(&config.toml_config);
+	let templates = Template::resolve_all(&run.template, &roots)?;
+	let parser = DefaultTemplateParse::with_vars(run.variables.clone()).with_partials(&roots.all());
+	let options = generate::Options::default();
+	// Older records may hold a relative output
+	let output = absolute(&run.output);
+	let rendered = render_all(&templates, &output, &parser, &options)?;
+	let guard = OutputGuard::new(&output, false, &run.variables)?;
+	guard.check_all(rendered_paths(&rendered))?;
+
+	// Files are only recorded by the run that created them, a later run overwriting them
+	// records them as modified
+	let runs = &mut history.runs[..=index];
+	let recorded = rendered_paths(&rendered).any(|path| find_record(runs, &absolute(path)).is_some());
+	if !recorded {
+		return Err(UpdateError::FilesNotFound(runs[index].template.clone()));
+	}
+
+	let mut updated = Updated {
+		template: runs[index].template.clone(),
+		..Default::default()
+	};
+	let mut rendered_paths = vec![];
+
+	for entry in rendered {
+		match entry {
+			Rendered::Directory(path) => {
+				if !path.exists() {
+					fs::create_dir_all(&path).map_err(|_| generate::Error::CouldNotWrite(path.clone()))?;
+					runs[index].directories.push(path);
+				}
+			}
+			// Appended content has no base of its own to merge with
+			Rendered::File {
+				mode: WriteMode::Append,
+				..
+			}
+			| Rendered::Skipped(_) => {}
+			Rendered::File { path, content, .. } => {
+				// Recorded paths are absolute, and canonicalized when they exist
+				let path = absolute(&path);
+				let content = String::from_utf8_lossy(&content).into_owned();
+				update_file(runs, path.clone(), content, &mut updated)?;
+				rendered_paths.push(path);
+			}
+		}
+	}
+
+	updated.stale = runs[index]
+		.files
+		.iter()
+		.filter(|file| !rendered_paths.contains(&file.path) && file.path.exists())
+		.map(|file| file.path.clone())
+		.collect();
+
+	history.write(history_path)?;
+
+	Ok(updated)
+}
+
+/// Latest record of `path`, the updated run being the last of `runs`
+fn find_record<'a>(runs: &'a mut [Run], path: &Path) -> Option<&'a mut FileRecord> {
+	runs
+		.iter_mut()
+		.rev()
+		.flat_map(|run| run.files.iter_mut())
+		.find(|file| file.path == path)
+}
+
+fn update_file(
+	runs: &mut [Run],
+	path: PathBuf,
+	template: String,
+	updated: &mut Updated,
+) -> Result<(), generate::Error> {
+	let record = find_record(runs, &path);
+	let current = fs::read_to_string(&path).ok();
+
+	let (record, current) = match (record, current) {
+		(Some(record), Some(current)) => (record, current),
+		// Deleted since the generation, the user does not want it
+		(Some(_), None) => return Ok(()),
+		(None, None) => {
+			fs::write(&path, &template).map_err(|_| generate::Error::CouldNotWrite(path.clone()))?;
+			let run = runs.last_mut().expect("the updated run");
+			run.files.push(FileRecord {
+				path: path.clone(),
+				hash: content_hash(template.as_bytes()),
+				content: Some(template),
+			});
+			updated.created.push(path);
+			return Ok(());
+		}
+		// Not generated by any recorded run, so it is not updated either
+		(None, Some(_)) => return Ok(()),
+	};
+
+	let base = match &record.content {
+		Some(base) => base.as_str(),
+		// Older records only kept a hash
+		None if record.is_unmodified() => current.as_str(),
+		None => "",
+	};
+
+	let merged = merge(base, &current, &template);
+	record.hash = content_hash(template.as_bytes());
+	record.content = Some(template);
+
+	if merged.content == current {
+		return Ok(());
+	}
+
+	if fs::write(&path, merged.content).is_err() {
+		return Err(generate::Error::CouldNotWrite(path));
+	}
+
+	if merged.conflicts > 0 {
+		updated.conflicted.push(path);
+	} else {
+		updated.merged.push(path);
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{commands::generate::generate, config::toml_config::TomlConfig};
+
+	#[test]
+	fn should_merge_template_changes_into_a_relative_output() {
+		let dir = PathBuf::from("./files/update");
+		let _ = fs::remove_dir_all(&dir);
+		let template = dir.join("templates/note");
+		fs::create_dir_all(&template).unwrap();
+		fs::write(template.join("{(name)}.md"), "# {(name)}\n\nbody\n").unwrap();
+
+		let config = || Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![dir.join("templates")],
+				..Default::default()
+			},
+		};
+		let output = dir.join("output/Foo");
+		let generated = generate(vec![], "note".into(), output, config(), Default::default()).unwrap();
+		let history_path = dir.join("history.toml");
+		History::record(&history_path, Run::new("note".into(), &generated).unwrap()).unwrap();
+
+		let file = dir.join("output/Foo.md");
+		fs::write(&file, "# Foo\n\nbody\nedited\n").unwrap();
+		fs::write(template.join("{(name)}.md"), "# {(name)} note\n\nbody\n").unwrap();
+
+		let updated = update(None, &history_path, config()).unwrap();
+		assert_eq!(updated.merged, vec![absolute(&file)]);
+		assert!(updated.stale.is_empty());
+		assert_eq!(
+			fs::read_to_string(&file).unwrap(),
+			"# Foo note\n\nbody\nedited\n"
+		);
+	}
+
+	#[test]
+	fn should_update_files_recorded_by_an_earlier_run() {
+		let dir = PathBuf::from("./files/update_rerun");
+		let _ = fs::remove_dir_all(&dir);
+		let template = dir.join("templates/note");
+		fs::create_dir_all(&template).unwrap();
+		fs::write(template.join("{(name)}.md"), "# {(name)}\n\nbody\n").unwrap();
+
+		let config = || Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![dir.join("templates")],
+				..Default::default()
+			},
+		};
+		let output = dir.join("output/Foo");
+		let history_path = dir.join("history.toml");
+		for _ in 0..2 {
+			let generated = generate(
+				vec![],
+				"note".into(),
+				output.clone(),
+				config(),
+				Default::default(),
+			)
+			.unwrap();
+			History::record(&history_path, Run::new("note".into(), &generated).unwrap()).unwrap();
+		}
+
+		let file = dir.join("output/Foo.md");
+		fs::write(&file, "# Foo\n\nbody\nedited\n").unwrap();
+		fs::write(template.join("{(name)}.md"), "# {(name)} note\n\nbody\n").unwrap();
+
+		let updated = update(None, &history_path, config()).unwrap();
+		assert_eq!(updated.merged, vec![absolute(&file)]);
+		assert_eq!(
+			fs::read_to_string(&file).unwrap(),
+			"# Foo note\n\nbody\nedited\n"
+		);
+	}
+
+	#[test]
+	fn should_fail_when_no_file_was_recorded() {
+		let dir = PathBuf::from("./files/update_unrecorded");
+		let _ = fs::remove_dir_all(&dir);
+		let template = dir.join("templates/note");
+		fs::create_dir_all(&template).unwrap();
+		fs::write(template.join("{(name)}.md"), "# {(name)}\n").unwrap();
+
+		let config = || Config {
+			toml_config: TomlConfig {
+				templates_paths: vec![dir.join("templates")],
+				..Default::default()
+			},
+		};
+		let output = dir.join("output/Foo");
+		let generated = generate(vec![], "note".into(), output, config(), Default::default()).unwrap();
+		let mut run = Run::new("note".into(), &generated).unwrap();
+		run.files.clear();
+		let history_path = dir.join("history.toml");
+		History::record(&history_path, run).unwrap();
+
+		assert!(matches!(
+			update(None, &history_path, config()),
+			Err(UpdateError::FilesNotFound(_))
+		));
+	}
+}
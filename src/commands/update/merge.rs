@@ -0,0 +1,138 @@
+pub const CONFLICT_START: &str = "<<<<<<< current";
+pub const CONFLICT_SEPARATOR: &str = "=======";
+pub const CONFLICT_END: &str = ">>>>>>> template";
+
+#[derive(Debug)]
+pub struct Merged {
+	pub content: String,
+	pub conflicts: usize,
+}
+
+/// Line based three way merge of the changes from `base` to `current` and from `base` to
+/// `template`. Chunks changed on both sides are left between conflict markers.
+pub fn merge(base: &str, current: &str, template: &str) -> Merged {
+	let base = base.split_inclusive('\n').collect::<Vec<_>>();
+	let current = current.split_inclusive('\n').collect::<Vec<_>>();
+	let template = template.split_inclusive('\n').collect::<Vec<_>>();
+
+	let current_matches = lcs_matches(&base, &current);
+	let template_matches = lcs_matches(&base, &template);
+
+	let mut merged = Merged {
+		content: String::new(),
+		conflicts: 0,
+	};
+
+	// Positions after the last line present in the three versions
+	let (mut b, mut c, mut t) = (0, 0, 0);
+
+	for (i, line) in base.iter().enumerate() {
+		let (ci, ti) = match (current_matches[i], template_matches[i]) {
+			(Some(ci), Some(ti)) => (ci, ti),
+			_ => continue,
+		};
+
+		merge_chunk(&mut merged, &base[b..i], &current[c..ci], &template[t..ti]);
+		merged.content.push_str(line);
+
+		(b, c, t) = (i + 1, ci + 1, ti + 1);
+	}
+
+	merge_chunk(&mut merged, &base[b..], &current[c..], &template[t..]);
+
+	merged
+}
+
+fn merge_chunk(merged: &mut Merged, base: &[&str], current: &[&str], template: &[&str]) {
+	if current == base || current == template {
+		template
+			.iter()
+			.for_each(|line| merged.content.push_str(line));
+		return;
+	}
+
+	if template == base {
+		current
+			.iter()
+			.for_each(|line| merged.content.push_str(line));
+		return;
+	}
+
+	merged.conflicts += 1;
+	push_side(&mut merged.content, CONFLICT_START, current);
+	push_side(&mut merged.content, CONFLICT_SEPARATOR, template);
+	merged.content.push_str(CONFLICT_END);
+	merged.content.push('\n');
+}
+
+fn push_side(content: &mut String, marker: &str, lines: &[&str]) {
+	content.push_str(marker);
+	content.push('\n');
+	lines.iter().for_each(|line| content.push_str(line));
+
+	if !content.ends_with('\n') {
+		content.push('\n');
+	}
+}
+
+/// For every line of `from`, the index of the line of `to` it is matched with in their longest
+/// common subsequence
+fn lcs_matches(from: &[&str], to: &[&str]) -> Vec<Option<usize>> {
+	let width = to.len() + 1;
+	let mut lengths = vec![0usize; (from.len() + 1) * width];
+
+	for i in (0..from.len()).rev() {
+		for j in (0..to.len()).rev() {
+			lengths[i * width + j] = if from[i] == to[j] {
+				lengths[(i + 1) * width + j + 1] + 1
+			} else {
+				lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+			};
+		}
+	}
+
+	let mut matches = vec![None; from.len()];
+	let (mut i, mut j) = (0, 0);
+	while i < from.len() && j < to.len() {
+		if from[i] == to[j] {
+			matches[i] = Some(j);
+			i += 1;
+			j += 1;
+		} else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+			i += 1;
+		} else {
+			j += 1;
+		}
+	}
+
+	matches
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_merge_independent_changes() {
+		let base = "import a;\n\nfn main() {}\n";
+		let current = "import a;\n\nfn main() { run() }\n";
+		let template = "import a;\nimport b;\n\nfn main() {}\n";
+
+		let merged = merge(base, current, template);
+		assert_eq!(merged.conflicts, 0);
+		assert_eq!(
+			merged.content,
+			"import a;\nimport b;\n\nfn main() { run() }\n"
+		);
+	}
+
+	#[test]
+	fn should_mark_conflicts() {
+		let merged = merge("a\nb\nc\n", "a\nB\nc\n", "a\nbee\nc\n");
+		assert_eq!(merged.conflicts, 1);
+		assert_eq!(
+			merged.content,
+			"a\n<<<<<<< current\nB\n=======\nbee\n>>>>>>> template\nc\n"
+		);
+	}
+}
@@ -6,5 +6,6 @@ pub enum ConfigError {
 	DirectoriesUnaccessable,
 
 	#[error(transparent)]
+	#[diagnostic(transparent)]
 	TomlConfigError(#[from] TomlConfigError),
 }
@@ -1,12 +1,20 @@
 mod error;
 pub mod toml_config;
+pub mod validate;
 
-use std::env::current_dir;
-use std::env::current_exe;
-use std::path::PathBuf;
+use std::env::{self, current_dir, current_exe};
+use std::path::{Path, PathBuf};
 
 use self::error::ConfigError;
-use self::toml_config::TomlConfig;
+use self::toml_config::{TomlConfig, CONFIG_FILENAME};
+
+/// Directory of the user-global config, under `$XDG_CONFIG_HOME`
+pub const CONFIG_DIRNAME: &str = "code-conjurer";
+
+/// Environment variable with the only config file to read, instead of discovering them
+pub const ENV_CONFIG: &str = "CCO_CONFIG";
+/// Environment variable with the profile to select, when `--profile` is not passed
+pub const ENV_PROFILE: &str = "CCO_PROFILE";
 
 #[derive(Debug)]
 pub struct Config {
@@ -14,22 +22,122 @@ pub struct Config {
 }
 
 impl Config {
-	pub fn try_new() -> Result<Self, ConfigError> {
-		let routes = [current_exe_dir(), current_dir()]
-			.into_iter()
-			.flatten()
-			.collect::<Vec<_>>();
+	/// Settings are layered, each layer overrides the previous ones:
+	/// 1. config files, from the most global to the most local, see [`config_dirs`], or only
+	///    `config_file`, or the file of `CCO_CONFIG`, when set. Each file is followed by its
+	///    section of `profile`, or of the profile of `CCO_PROFILE`,
+	/// 2. environment variables, `CCO_TEMPLATES_PATH` and `CCO_VAR_<NAME>`, see
+	///    [`TomlConfig::from_files`],
+	/// 3. variables passed with `-p` to `generate`.
+	pub fn try_new(
+		config_file: Option<PathBuf>,
+		profile: Option<String>,
+	) -> Result<Self, ConfigError> {
+		let profile = selected_profile(profile);
+
+		if let Some(file) = named_file(config_file) {
+			let toml_config = TomlConfig::try_new_from_file(file, profile.as_deref())?;
+			return Ok(Config { toml_config });
+		}
 
-		if routes.len() == 0 {
+		let routes = config_dirs();
+
+		if routes.is_empty() {
 			return Err(ConfigError::DirectoriesUnaccessable);
 		}
 
-		let toml_config = TomlConfig::try_new(&routes)?;
+		let toml_config = TomlConfig::try_new(&routes, profile.as_deref())?;
 
 		Ok(Config { toml_config })
 	}
 }
 
+/// The profile of `profile`, or of `CCO_PROFILE`
+pub fn selected_profile(profile: Option<String>) -> Option<String> {
+	profile.or_else(|| env::var(ENV_PROFILE).ok())
+}
+
+/// The config file of `config_file`, or of `CCO_CONFIG`
+fn named_file(config_file: Option<PathBuf>) -> Option<PathBuf> {
+	config_file.or_else(|| env::var_os(ENV_CONFIG).map(PathBuf::from))
+}
+
+/// Config files [`Config::try_new`] looks up, to check them without reading the config
+pub fn config_files(config_file: Option<PathBuf>) -> Result<Vec<PathBuf>, ConfigError> {
+	if let Some(file) = named_file(config_file) {
+		return Ok(vec![file]);
+	}
+
+	let routes = config_dirs();
+	if routes.is_empty() {
+		return Err(ConfigError::DirectoriesUnaccessable);
+	}
+
+	Ok(routes.iter().map(|dir| dir.join(CONFIG_FILENAME)).collect())
+}
+
+/// Directories where config files are looked up, from the most global to the most local, so
+/// configs of nested projects override the ones of their parents:
+/// * the directory of the executable,
+/// * the ancestors of the current directory that are also ancestors of `$HOME`, like `/home`,
+/// * `$XDG_CONFIG_HOME/code-conjurer/`, or `$HOME/.config/code-conjurer/`,
+/// * `$HOME`,
+/// * the rest of the ancestors of the current directory, down to the current directory.
+fn config_dirs() -> Vec<PathBuf> {
+	let home = env::var_os("HOME").map(PathBuf::from);
+	let cwd = current_dir().ok();
+
+	order_dirs(
+		current_exe_dir().ok(),
+		user_config_dir(),
+		home,
+		cwd.as_deref(),
+	)
+}
+
+fn order_dirs(
+	exe_dir: Option<PathBuf>,
+	user_config_dir: Option<PathBuf>,
+	home: Option<PathBuf>,
+	cwd: Option<&Path>,
+) -> Vec<PathBuf> {
+	let mut ancestors = cwd
+		.map(|dir| dir.ancestors().map(Path::to_path_buf).collect::<Vec<_>>())
+		.unwrap_or_default();
+	ancestors.reverse();
+
+	let (above_home, below_home): (Vec<_>, Vec<_>) = ancestors.into_iter().partition(|dir| {
+		home
+			.as_ref()
+			.is_some_and(|home| home != dir && home.starts_with(dir))
+	});
+
+	let mut dirs: Vec<PathBuf> = vec![];
+	let candidates = exe_dir
+		.into_iter()
+		.chain(above_home)
+		.chain(user_config_dir)
+		.chain(home)
+		.chain(below_home);
+	for dir in candidates {
+		if !dirs.contains(&dir) {
+			dirs.push(dir);
+		}
+	}
+
+	dirs
+}
+
+/// Directory of the user-global config, `$XDG_CONFIG_HOME/code-conjurer/`, or
+/// `$HOME/.config/code-conjurer/`
+pub fn user_config_dir() -> Option<PathBuf> {
+	env::var_os("XDG_CONFIG_HOME")
+		.map(PathBuf::from)
+		.filter(|path| path.is_absolute())
+		.or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+		.map(|path| path.join(CONFIG_DIRNAME))
+}
+
 fn current_exe_dir() -> std::io::Result<PathBuf> {
 	let exe_path = current_exe()?;
 	let exe_dir = exe_path
@@ -38,3 +146,31 @@ fn current_exe_dir() -> std::io::Result<PathBuf> {
 
 	Ok(exe_dir.to_owned())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_read_home_after_its_ancestors() {
+		let dirs = order_dirs(
+			Some(PathBuf::from("/usr/bin")),
+			Some(PathBuf::from("/tmp/home/.config/code-conjurer")),
+			Some(PathBuf::from("/tmp/home")),
+			Some(Path::new("/tmp/home/proj")),
+		);
+
+		assert_eq!(
+			dirs,
+			[
+				"/usr/bin",
+				"/",
+				"/tmp",
+				"/tmp/home/.config/code-conjurer",
+				"/tmp/home",
+				"/tmp/home/proj"
+			]
+			.map(PathBuf::from)
+		);
+	}
+}
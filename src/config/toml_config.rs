@@ -1,91 +1,385 @@
 mod error;
+mod keys;
 
-use std::{borrow::Cow, collections::HashMap, fs, path::PathBuf};
+use std::{
+	borrow::Cow,
+	collections::HashMap,
+	fmt::Display,
+	fs,
+	path::{Path, PathBuf},
+};
 
+use miette::NamedSource;
 use serde::Deserialize;
 
 pub use error::{NotFoundIn, TomlConfigError};
+pub use keys::{key_span, suggest};
 
 pub const CONFIG_FILENAME: &str = ".codecrc.toml";
 
+/// Environment variable with templates directories that shadow the ones of config files
+pub const ENV_TEMPLATES_PATH: &str = "CCO_TEMPLATES_PATH";
+/// Prefix of environment variables that set a template variable, like `CCO_VAR_AUTHOR`
+pub const ENV_VAR_PREFIX: &str = "CCO_VAR_";
+
+/// Key of the table of profiles, like `[profiles.work]`
+pub const PROFILES_KEY: &str = "profiles";
+/// Every key a config file can set, see [`PartialTomlConfig`]
+pub const CONFIG_KEYS: [&str; 5] = [
+	"templates_path",
+	"variables",
+	"unset",
+	"namespaces",
+	PROFILES_KEY,
+];
+
 #[derive(Deserialize, Debug, Default)]
 pub struct PartialTomlConfig {
+	/// One templates directory or a list of them, the first ones shadow templates with the same
+	/// name in the next ones
 	#[serde(default)]
-	#[serde(deserialize_with = "partial_deserialize_path")]
-	pub templates_path: Option<PathBuf>,
+	#[serde(deserialize_with = "partial_deserialize_paths")]
+	pub templates_path: Option<Vec<PathBuf>>,
 	#[serde(default)]
 	pub variables: Option<HashMap<String, String>>,
+	/// Variables of earlier config files to remove, like `unset = ["author"]`
+	#[serde(default)]
+	pub unset: Option<Vec<String>>,
+	/// Templates directories referenced with a prefix, like `team:react/component`
+	#[serde(default)]
+	#[serde(deserialize_with = "partial_deserialize_namespaces")]
+	pub namespaces: Option<HashMap<String, PathBuf>>,
+	/// Settings layered over the rest of the file when their profile is selected, like
+	/// `[profiles.work]`
+	#[serde(default)]
+	pub profiles: Option<HashMap<String, PartialTomlConfig>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct TomlConfig {
-	pub templates_path: PathBuf,
+	/// Directories where templates are looked up, in order
+	pub templates_paths: Vec<PathBuf>,
+	/// Templates directories only looked up for templates with their namespace prefix
+	pub namespaces: HashMap<String, PathBuf>,
 	pub variables: HashMap<String, String>,
+	pub sources: ConfigSources,
+}
+
+/// Config files that were looked up and read, and where each effective setting came from
+#[derive(Debug, Default, Clone)]
+pub struct ConfigSources {
+	/// Every config file that was looked up, read or not
+	pub considered: Vec<PathBuf>,
+	pub files: Vec<PathBuf>,
+	pub templates_paths: HashMap<PathBuf, Source>,
+	pub namespaces: HashMap<String, Source>,
+	pub variables: HashMap<String, Source>,
+}
+
+/// Where a setting came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+	File(PathBuf),
+	/// An environment variable, like `CCO_VAR_AUTHOR`
+	Env(String),
+}
+
+impl Display for Source {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Source::File(path) => write!(f, "{}", path.to_string_lossy()),
+			Source::Env(name) => write!(f, "${name}"),
+		}
+	}
 }
 
-fn partial_deserialize_path<'de, D>(deserializer: D) -> Result<Option<PathBuf>, D::Error>
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+	One(String),
+	Many(Vec<String>),
+}
+
+fn partial_deserialize_paths<'de, D>(deserializer: D) -> Result<Option<Vec<PathBuf>>, D::Error>
 where
 	D: serde::Deserializer<'de>,
 {
-	let may_path = Option::<String>::deserialize(deserializer)?;
-	match may_path {
-		None => Ok(None),
-		Some(path) => {
-			let expanded = match shellexpand::full(&path) {
-				Ok(expanded) => expanded,
-				Err(e) => return Err(serde::de::Error::custom(e)),
-			};
+	let may_paths = match Option::<OneOrMany>::deserialize(deserializer)? {
+		None => return Ok(None),
+		Some(OneOrMany::One(path)) => vec![path],
+		Some(OneOrMany::Many(paths)) => paths,
+	};
 
-			let templates_path = match expanded {
-				Cow::Owned(owned) => PathBuf::from(owned),
-				Cow::Borrowed(borrowed) => PathBuf::from(borrowed),
-			};
+	let mut templates_paths = vec![];
+	for path in may_paths {
+		templates_paths.push(expand_path(&path).map_err(serde::de::Error::custom)?);
+	}
 
-			Ok(Some(templates_path))
-		}
+	Ok(Some(templates_paths))
+}
+
+fn partial_deserialize_namespaces<'de, D>(
+	deserializer: D,
+) -> Result<Option<HashMap<String, PathBuf>>, D::Error>
+where
+	D: serde::Deserializer<'de>,
+{
+	let may_namespaces = match Option::<HashMap<String, String>>::deserialize(deserializer)? {
+		None => return Ok(None),
+		Some(namespaces) => namespaces,
+	};
+
+	let mut namespaces = HashMap::new();
+	for (namespace, path) in may_namespaces {
+		let path = expand_path(&path).map_err(serde::de::Error::custom)?;
+		namespaces.insert(namespace, path);
 	}
+
+	Ok(Some(namespaces))
 }
 
-impl TomlConfig {
-	pub fn try_new(choices: &[PathBuf]) -> Result<Self, TomlConfigError> {
-		let mut base_config = PartialTomlConfig::default();
-		let mut found_any = false;
+fn expand_path(path: &str) -> Result<PathBuf, shellexpand::LookupError<std::env::VarError>> {
+	let expanded = shellexpand::full(path)?;
 
+	let path = match expanded {
+		Cow::Owned(owned) => PathBuf::from(owned),
+		Cow::Borrowed(borrowed) => PathBuf::from(borrowed),
+	};
+
+	Ok(path)
+}
+
+impl TomlConfig {
+	/// Reads the config file of every directory of `choices` that has one, then the environment,
+	/// see [`TomlConfig::from_files`]
+	pub fn try_new(choices: &[PathBuf], profile: Option<&str>) -> Result<Self, TomlConfigError> {
 		let files = choices
 			.iter()
 			.map(|choice| choice.join(CONFIG_FILENAME))
 			.collect::<Vec<_>>();
 
-		for content in files.iter().flat_map(fs::read_to_string) {
-			let added_config = toml::from_str::<PartialTomlConfig>(&content)?;
-			if added_config.templates_path.is_some() {
-				base_config.templates_path = added_config.templates_path;
-			}
+		Self::from_files(&files, profile, env_vars())
+	}
+
+	/// Reads only `file`, then the environment, see [`TomlConfig::from_files`]. Unlike discovered
+	/// files, `file` was named by the user, so it must be readable
+	pub fn try_new_from_file(file: PathBuf, profile: Option<&str>) -> Result<Self, TomlConfigError> {
+		let readable = fs::File::open(&file)
+			.and_then(|file| file.metadata())
+			.is_ok_and(|metadata| metadata.is_file());
+		if !readable {
+			return Err(TomlConfigError::Unreadable(file));
+		}
 
-			if added_config.variables.is_some() {
-				base_config.variables = added_config.variables;
+		Self::from_files(&[file], profile, env_vars())
+	}
+
+	/// Reads every file of `files` that exists, later files override the settings of earlier
+	/// ones, variables are overriden one by one and templates directories of later files shadow
+	/// the ones of earlier files. The section of `profile` of each file is layered right over
+	/// the rest of the file. The environment variables of `env` are layered over the files:
+	/// * `CCO_TEMPLATES_PATH`: templates directories, separated like in `PATH`,
+	/// * `CCO_VAR_<NAME>`: the variable `name`, names are lowercased.
+	pub fn from_files(
+		files: &[PathBuf],
+		profile: Option<&str>,
+		env: impl IntoIterator<Item = (String, String)>,
+	) -> Result<Self, TomlConfigError> {
+		let mut base_config = PartialTomlConfig::default();
+		let mut sources = ConfigSources {
+			considered: files.to_vec(),
+			..Default::default()
+		};
+		let mut found_profile = false;
+
+		for file in files {
+			let content = match fs::read_to_string(file) {
+				Ok(content) => content,
+				Err(_) => continue,
+			};
+
+			let mut added_config = parse_file(file, &content)?;
+			let selected = profile.and_then(|profile| {
+				let profiles = added_config.profiles.as_mut()?;
+				profiles.remove(profile)
+			});
+
+			let source = Source::File(file.clone());
+			layer_file(&mut base_config, &mut sources, added_config, file, &source);
+			if let Some(selected) = selected {
+				layer_file(&mut base_config, &mut sources, selected, file, &source);
+				found_profile = true;
 			}
 
-			found_any = true;
+			sources.files.push(file.clone());
+		}
+
+		if let Some(profile) = profile.filter(|_| !found_profile) {
+			return Err(TomlConfigError::ProfileNotFound(profile.to_owned()));
 		}
 
-		if !found_any {
+		for (key, value) in env {
+			let source = Source::Env(key.clone());
+			if key == ENV_TEMPLATES_PATH {
+				let mut added_paths = vec![];
+				for path in std::env::split_paths(&value) {
+					let path = expand_path(&path.to_string_lossy())
+						.map_err(|_| TomlConfigError::EnvInvalid(key.clone()))?;
+					added_paths.push(path);
+				}
+				layer_templates_paths(&mut base_config, &mut sources, added_paths, &source);
+			} else if let Some(name) = key.strip_prefix(ENV_VAR_PREFIX) {
+				let name = name.to_lowercase();
+				let variables = base_config.variables.get_or_insert_with(HashMap::new);
+				sources.variables.insert(name.clone(), source);
+				variables.insert(name, value);
+			}
+		}
+
+		if sources.files.is_empty() && base_config.templates_path.is_none() {
 			return Err(NotFoundIn(files.into()).into());
 		}
 
-		Self::try_from(base_config)
+		let mut config =
+			Self::try_from(base_config).map_err(|field| missing_field(field, &sources.files))?;
+		config.sources = sources;
+		Ok(config)
+	}
+}
+
+/// Layers the settings of a config `file` over the ones of earlier files
+fn layer_file(
+	base_config: &mut PartialTomlConfig,
+	sources: &mut ConfigSources,
+	added_config: PartialTomlConfig,
+	file: &Path,
+	source: &Source,
+) {
+	// Relative to the config file, so it works from any directory under it
+	let dir = file.parent().unwrap_or(Path::new(""));
+
+	if let Some(added_paths) = added_config.templates_path {
+		let added_paths = added_paths
+			.into_iter()
+			.map(|templates_path| dir.join(templates_path))
+			.collect();
+		layer_templates_paths(base_config, sources, added_paths, source);
+	}
+
+	let namespaces = base_config.namespaces.get_or_insert_with(HashMap::new);
+	for (namespace, path) in added_config.namespaces.unwrap_or_default() {
+		sources.namespaces.insert(namespace.clone(), source.clone());
+		namespaces.insert(namespace, dir.join(path));
+	}
+
+	let variables = base_config.variables.get_or_insert_with(HashMap::new);
+	for name in added_config.unset.unwrap_or_default() {
+		variables.remove(&name);
+		sources.variables.remove(&name);
+	}
+
+	for (name, value) in added_config.variables.unwrap_or_default() {
+		sources.variables.insert(name.clone(), source.clone());
+		variables.insert(name, value);
+	}
+}
+
+/// Puts `added_paths` before the templates directories of earlier layers, so they shadow them
+fn layer_templates_paths(
+	base_config: &mut PartialTomlConfig,
+	sources: &mut ConfigSources,
+	mut added_paths: Vec<PathBuf>,
+	source: &Source,
+) {
+	for templates_path in added_paths.iter() {
+		sources
+			.templates_paths
+			.insert(templates_path.clone(), source.clone());
+	}
+
+	let earlier_paths = base_config.templates_path.take().unwrap_or_default();
+	for templates_path in earlier_paths {
+		if !added_paths.contains(&templates_path) {
+			added_paths.push(templates_path);
+		}
+	}
+	base_config.templates_path = Some(added_paths);
+}
+
+/// Points at the misspelling of `field` in the most local config file that has one, or at the
+/// most local config file
+fn missing_field(field: &'static str, files: &[PathBuf]) -> TomlConfigError {
+	let read = files
+		.iter()
+		.rev()
+		.filter_map(|file| Some((file, fs::read_to_string(file).ok()?)))
+		.collect::<Vec<_>>();
+
+	let misspelled = read.iter().find_map(|(file, content)| {
+		let table = content.parse::<toml::Table>().ok()?;
+		let key = table
+			.keys()
+			.find(|key| *key != field && suggest(key, &CONFIG_KEYS) == Some(field))?;
+		Some((*file, content, key_span(content, None, key)?))
+	});
+
+	let (file, content, misspelled) = match misspelled {
+		Some((file, content, span)) => (Some(file), content.clone(), Some(span)),
+		None => match read.first() {
+			Some((file, content)) => (Some(*file), content.clone(), None),
+			None => (None, String::new(), None),
+		},
+	};
+
+	let advice = match misspelled {
+		Some(_) => format!("Rename the key to `{field}`."),
+		None => {
+			let files = files.iter().map(|file| file.to_string_lossy());
+			let files = files.collect::<Vec<_>>().join(", ");
+			format!("Set `{field}` in one of the config files: {files}")
+		}
+	};
+
+	TomlConfigError::MissingField {
+		field,
+		src: NamedSource::new(
+			file.map(|file| file.to_string_lossy()).unwrap_or_default(),
+			content,
+		),
+		misspelled,
+		advice,
 	}
 }
 
+/// Parses the `content` of the config `file`, without layering it
+pub fn parse_file(file: &Path, content: &str) -> Result<PartialTomlConfig, TomlConfigError> {
+	toml::from_str::<PartialTomlConfig>(content).map_err(|error| TomlConfigError::Unparseable {
+		message: error.message().to_owned(),
+		span: error.span().map(|span| (span.start, span.len())),
+		src: NamedSource::new(file.to_string_lossy(), content.to_owned()),
+	})
+}
+
+/// Environment variables with a unicode name and value
+pub fn env_vars() -> Vec<(String, String)> {
+	std::env::vars_os()
+		.filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+		.collect()
+}
+
 impl TryFrom<PartialTomlConfig> for TomlConfig {
-	type Error = TomlConfigError;
+	/// The missing field
+	type Error = &'static str;
 
 	fn try_from(value: PartialTomlConfig) -> Result<Self, Self::Error> {
 		Ok(Self {
-			templates_path: value
+			templates_paths: value
 				.templates_path
-				.ok_or_else(|| TomlConfigError::MissingField("templates_path"))?,
+				.filter(|templates_paths| !templates_paths.is_empty())
+				.ok_or("templates_path")?,
+			namespaces: value.namespaces.unwrap_or_default(),
 			variables: value.variables.unwrap_or_default(),
+			sources: ConfigSources::default(),
 		})
 	}
 }
@@ -101,7 +395,133 @@ mod tests {
 			PathBuf::from("./tests/mock-config/bar/"),
 		];
 
-		let config = TomlConfig::try_new(&paths).unwrap();
+		let config = TomlConfig::try_new(&paths, None).unwrap();
 		assert_eq!(config.variables["namespace"], "foo");
 	}
+
+	#[test]
+	fn should_merge_config_files() {
+		fs::create_dir_all("./files/config/root/package").unwrap();
+		fs::write(
+			"./files/config/root/.codecrc.toml",
+			"templates_path = \"templates\"\n[variables]\nnamespace = \"root\"\nauthor = \"me\"\nlicense = \"MIT\"",
+		)
+		.unwrap();
+		fs::write(
+			"./files/config/root/package/.codecrc.toml",
+			"templates_path = [\"templates\"]\nunset = [\"license\"]\n[variables]\nnamespace = \"package\"",
+		)
+		.unwrap();
+
+		let paths = [
+			PathBuf::from("./files/config/root/"),
+			PathBuf::from("./files/config/root/package/"),
+		];
+
+		let config = TomlConfig::try_new(&paths, None).unwrap();
+		assert_eq!(
+			config.templates_paths,
+			vec![
+				PathBuf::from("./files/config/root/package/templates"),
+				PathBuf::from("./files/config/root/templates"),
+			]
+		);
+		assert_eq!(config.variables["namespace"], "package");
+		assert_eq!(config.variables["author"], "me");
+		assert!(!config.variables.contains_key("license"));
+		assert_eq!(
+			config.sources.variables["author"],
+			Source::File(PathBuf::from("./files/config/root/.codecrc.toml"))
+		);
+	}
+
+	#[test]
+	fn should_point_at_misspelled_fields() {
+		fs::create_dir_all("./files/config/misspelled").unwrap();
+		fs::write(
+			"./files/config/misspelled/.codecrc.toml",
+			"[variables]\nauthor = \"me\"\n",
+		)
+		.unwrap();
+		fs::write(
+			"./files/config/misspelled/.local.toml",
+			"template_path = \"templates\"\n",
+		)
+		.unwrap();
+
+		let files = [
+			PathBuf::from("./files/config/misspelled/.local.toml"),
+			PathBuf::from("./files/config/misspelled/.codecrc.toml"),
+		];
+		let Err(TomlConfigError::MissingField {
+			misspelled, advice, ..
+		}) = TomlConfig::from_files(&files, None, [])
+		else {
+			panic!("Misspelled templates_path was read");
+		};
+		assert_eq!(misspelled, Some((0, 13)));
+		assert_eq!(advice, "Rename the key to `templates_path`.");
+	}
+
+	#[test]
+	fn should_layer_environment_over_files() {
+		fs::create_dir_all("./files/config/env").unwrap();
+		fs::write(
+			"./files/config/env/.codecrc.toml",
+			"templates_path = \"templates\"\n[variables]\nauthor = \"file\"",
+		)
+		.unwrap();
+
+		let env = [
+			("CCO_TEMPLATES_PATH".to_owned(), "/tmp/a:/tmp/b".to_owned()),
+			("CCO_VAR_AUTHOR".to_owned(), "env".to_owned()),
+			("HOME".to_owned(), "/home".to_owned()),
+		];
+
+		let files = [PathBuf::from("./files/config/env/.codecrc.toml")];
+		let config = TomlConfig::from_files(&files, None, env).unwrap();
+		assert_eq!(
+			config.templates_paths,
+			vec![
+				PathBuf::from("/tmp/a"),
+				PathBuf::from("/tmp/b"),
+				PathBuf::from("./files/config/env/templates"),
+			]
+		);
+		assert_eq!(config.variables["author"], "env");
+		assert_eq!(
+			config.sources.variables["author"],
+			Source::Env("CCO_VAR_AUTHOR".to_owned())
+		);
+		assert!(!config.variables.contains_key("home"));
+	}
+
+	#[test]
+	fn should_layer_selected_profile() {
+		fs::create_dir_all("./files/config/profiles").unwrap();
+		fs::write(
+			"./files/config/profiles/.codecrc.toml",
+			"templates_path = \"templates\"\n[variables]\nauthor = \"me\"\n\n\
+			[profiles.work]\ntemplates_path = \"work\"\nvariables = { author = \"team\" }",
+		)
+		.unwrap();
+
+		let files = [PathBuf::from("./files/config/profiles/.codecrc.toml")];
+		let config = TomlConfig::from_files(&files, Some("work"), []).unwrap();
+		assert_eq!(config.variables["author"], "team");
+		assert_eq!(
+			config.templates_paths[0],
+			PathBuf::from("./files/config/profiles/work")
+		);
+
+		let error = TomlConfig::from_files(&files, Some("home"), []).unwrap_err();
+		assert!(matches!(error, TomlConfigError::ProfileNotFound(_)));
+	}
+
+	#[test]
+	fn should_require_the_named_file() {
+		let file = PathBuf::from("./files/config/missing/.codecrc.toml");
+		let error = TomlConfig::try_new_from_file(file, None).unwrap_err();
+		assert!(matches!(error, TomlConfigError::Unreadable(_)));
+	}
 }
@@ -1,15 +1,47 @@
 use std::path::PathBuf;
 
-#[derive(Debug, thiserror::Error)]
+use miette::NamedSource;
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
 pub enum TomlConfigError {
 	#[error(transparent)]
 	NotFoundIn(#[from] NotFoundIn),
 
-	#[error("Toml config unparseable: \n{0}")]
-	Unparseable(#[from] toml::de::Error),
+	#[error("Toml config unparseable: {message}")]
+	#[diagnostic(code(config::Unparseable))]
+	Unparseable {
+		message: String,
+		#[source_code]
+		src: NamedSource,
+		#[label("here")]
+		span: Option<(usize, usize)>,
+	},
+
+	#[error("Toml config missing field: {field}")]
+	#[diagnostic(code(config::MissingField))]
+	MissingField {
+		field: &'static str,
+		/// The most local config file, or the one with a misspelling of `field`
+		#[source_code]
+		src: NamedSource,
+		#[label("did you mean `{field}`?")]
+		misspelled: Option<(usize, usize)>,
+		#[help]
+		advice: String,
+	},
+
+	#[error("Config file '{}' could not be read", .0.to_string_lossy())]
+	#[diagnostic(
+		code(config::Unreadable),
+		help("Files passed with --config or CCO_CONFIG must exist.")
+	)]
+	Unreadable(PathBuf),
+
+	#[error("Environment variable {0} is invalid")]
+	EnvInvalid(String),
 
-    #[error("Toml config missing field: {0}")]
-    MissingField(&'static str),
+	#[error("Profile '{0}' is not defined in any config file")]
+	ProfileNotFound(String),
 }
 
 #[derive(Debug)]
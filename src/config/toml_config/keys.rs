@@ -0,0 +1,113 @@
+/// The key of `known` that `key` is most likely a misspelling of, like `templates_path` for
+/// `template_path`
+pub fn suggest(key: &str, known: &[&'static str]) -> Option<&'static str> {
+	// Tolerate a typo every few characters, so short keys are not matched by anything
+	let tolerance = (key.len() / 4).max(1);
+
+	known
+		.iter()
+		.map(|known| (*known, distance(key, known)))
+		.filter(|(_, distance)| *distance <= tolerance)
+		.min_by_key(|(_, distance)| *distance)
+		.map(|(known, _)| known)
+}
+
+/// Offset and length of `key` in the content of a toml file. `key` is looked up in the sections
+/// with the header `[table]`, like `[profiles.work]`, or at the top of the file, where it can be
+/// a table itself, like `[variabes]`. Arrays of tables, like `[[inject]]`, are passed as
+/// `[inject]`.
+pub fn key_span(content: &str, table: Option<&str>, key: &str) -> Option<(usize, usize)> {
+	let header = table.map(|table| format!("[{table}]"));
+	let sections = match &header {
+		Some(header) => content
+			.match_indices(header.as_str())
+			.map(|(start, _)| start + header.len())
+			.collect(),
+		None => vec![0],
+	};
+
+	sections
+		.into_iter()
+		.find_map(|section| section_key_span(content, section, table.is_none(), key))
+}
+
+/// Looks `key` up from `offset` to the next table header
+fn section_key_span(
+	content: &str,
+	mut offset: usize,
+	is_top: bool,
+	key: &str,
+) -> Option<(usize, usize)> {
+	for line in content[offset..].split_inclusive('\n') {
+		let trimmed = line.trim_start();
+		let start = offset + line.len() - trimmed.len();
+
+		if let Some(header) = trimmed.strip_prefix('[') {
+			let is_table = header
+				.strip_prefix(key)
+				.is_some_and(|rest| rest.starts_with([']', '.']));
+			if is_top && is_table {
+				return Some((start + 1, key.len()));
+			}
+			// Keys of the section end at the next one
+			return None;
+		}
+
+		let is_key = trimmed
+			.strip_prefix(key)
+			.is_some_and(|rest| rest.trim_start().starts_with('='));
+		if is_key {
+			return Some((start, key.len()));
+		}
+
+		offset += line.len();
+	}
+
+	None
+}
+
+/// Edit distance between `a` and `b`, in characters
+fn distance(a: &str, b: &str) -> usize {
+	let b = b.chars().collect::<Vec<_>>();
+	let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+	for (i, a_char) in a.chars().enumerate() {
+		let mut current = vec![i + 1];
+		for (j, b_char) in b.iter().enumerate() {
+			let substitution = previous[j] + usize::from(a_char != *b_char);
+			current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+		}
+		previous = current;
+	}
+
+	previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::toml_config::CONFIG_KEYS;
+
+	#[test]
+	fn should_suggest_misspelled_keys() {
+		assert_eq!(
+			suggest("template_path", &CONFIG_KEYS),
+			Some("templates_path")
+		);
+		assert_eq!(suggest("variabes", &CONFIG_KEYS), Some("variables"));
+		assert_eq!(suggest("author", &CONFIG_KEYS), None);
+	}
+
+	#[test]
+	fn should_find_keys_of_tables() {
+		let content =
+			"template_path = \"a\"\n[variabes]\nauthor = \"me\"\n[profiles.work]\nauthor = \"work\"";
+		assert_eq!(key_span(content, None, "template_path"), Some((0, 13)));
+		assert_eq!(key_span(content, None, "variabes"), Some((21, 8)));
+		assert_eq!(
+			key_span(content, Some("profiles.work"), "author"),
+			Some((61, 6))
+		);
+		assert_eq!(key_span(content, None, "author"), None);
+	}
+}
@@ -0,0 +1,221 @@
+use std::{
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+use miette::NamedSource;
+
+use super::toml_config::{
+	env_vars, key_span, parse_file, suggest, TomlConfig, TomlConfigError, CONFIG_KEYS, PROFILES_KEY,
+};
+
+/// A mistake in the config files
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum ConfigProblem {
+	#[error("Unknown key '{key}' in {}", file.to_string_lossy())]
+	#[diagnostic(code(config::UnknownKey))]
+	UnknownKey {
+		key: String,
+		file: PathBuf,
+		#[source_code]
+		src: NamedSource,
+		#[label("unknown key")]
+		span: Option<(usize, usize)>,
+		#[help]
+		advice: String,
+	},
+
+	#[error("Templates directory {} does not exist", .0.to_string_lossy())]
+	#[diagnostic(code(config::TemplatesPathNotFound))]
+	TemplatesPathNotFound(PathBuf),
+
+	#[error("Directory {} of namespace '{namespace}' does not exist", path.to_string_lossy())]
+	#[diagnostic(code(config::NamespaceNotFound))]
+	NamespaceNotFound { namespace: String, path: PathBuf },
+
+	#[error(transparent)]
+	#[diagnostic(transparent)]
+	Config(#[from] TomlConfigError),
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+#[error("Config has {} problems", problems.len())]
+#[diagnostic(code(config::ConfigInvalid))]
+pub struct ConfigInvalid {
+	#[related]
+	pub problems: Vec<ConfigProblem>,
+}
+
+/// Parses each of `files` that exists on its own, so every broken file is reported, and checks
+/// their keys. Once all of them can be read, checks the config they make with `profile`, and that
+/// its directories exist.
+pub fn validate(files: &[PathBuf], profile: Option<&str>) -> Result<(), ConfigInvalid> {
+	let mut problems = vec![];
+	let mut readable = true;
+
+	for file in files {
+		let content = match fs::read_to_string(file) {
+			Ok(content) => content,
+			// Discovered files do not need to exist
+			Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+			Err(_) => {
+				problems.push(TomlConfigError::Unreadable(file.clone()).into());
+				readable = false;
+				continue;
+			}
+		};
+
+		if let Err(error) = parse_file(file, &content) {
+			problems.push(error.into());
+			readable = false;
+		}
+		// Keys are checked in every valid toml, even with values of the wrong type
+		problems.extend(unknown_keys(file, &content));
+	}
+
+	// Reading the config stops at the first broken file, which was already reported
+	if readable {
+		match TomlConfig::from_files(files, profile, env_vars()) {
+			Ok(config) => problems.extend(missing_directories(&config)),
+			Err(error) => problems.push(error.into()),
+		}
+	}
+
+	match problems.is_empty() {
+		true => Ok(()),
+		false => Err(ConfigInvalid { problems }),
+	}
+}
+
+fn missing_directories(config: &TomlConfig) -> Vec<ConfigProblem> {
+	let mut problems = vec![];
+
+	for templates_path in config.templates_paths.iter() {
+		if !templates_path.is_dir() {
+			problems.push(ConfigProblem::TemplatesPathNotFound(templates_path.clone()));
+		}
+	}
+
+	let mut namespaces = config.namespaces.iter().collect::<Vec<_>>();
+	namespaces.sort();
+	for (namespace, path) in namespaces {
+		if !path.is_dir() {
+			problems.push(ConfigProblem::NamespaceNotFound {
+				namespace: namespace.clone(),
+				path: path.clone(),
+			});
+		}
+	}
+
+	problems
+}
+
+fn unknown_keys(file: &Path, content: &str) -> Vec<ConfigProblem> {
+	let table = match content.parse::<toml::Table>() {
+		Ok(table) => table,
+		Err(_) => return vec![],
+	};
+
+	let mut unknown = vec![];
+	for (key, value) in table.iter() {
+		if !CONFIG_KEYS.contains(&key.as_str()) {
+			unknown.push(key.clone());
+		}
+		if key != PROFILES_KEY {
+			continue;
+		}
+
+		for (profile, settings) in value.as_table().into_iter().flatten() {
+			// Profiles can not be nested
+			let keys = settings
+				.as_table()
+				.into_iter()
+				.flatten()
+				.map(|(key, _)| key);
+			unknown.extend(
+				keys
+					.filter(|key| *key == PROFILES_KEY || !CONFIG_KEYS.contains(&key.as_str()))
+					.map(|key| format!("{PROFILES_KEY}.{profile}.{key}")),
+			);
+		}
+	}
+
+	unknown
+		.into_iter()
+		.map(|key| {
+			let (table, name) = match key.rsplit_once('.') {
+				Some((table, name)) => (Some(table), name),
+				None => (None, key.as_str()),
+			};
+			// Profiles can not be nested, suggesting them inside of a profile does not help
+			let suggestion = suggest(name, &CONFIG_KEYS);
+			let advice = match suggestion.filter(|known| *known != PROFILES_KEY || table.is_none()) {
+				Some(known) => format!("Did you mean `{known}`?"),
+				None => format!("Known keys: {}", CONFIG_KEYS.join(", ")),
+			};
+
+			ConfigProblem::UnknownKey {
+				span: key_span(content, table, name),
+				src: NamedSource::new(file.to_string_lossy(), content.to_owned()),
+				advice,
+				key,
+				file: file.to_path_buf(),
+			}
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_report_every_problem() {
+		fs::create_dir_all("./files/config/validate/templates").unwrap();
+		fs::write(
+			"./files/config/validate/.codecrc.toml",
+			"templates_path = [\"templates\", \"missing\"]\ntemplate_path = \"templates\"\n[profiles.work]\nauthor = \"me\"",
+		)
+		.unwrap();
+
+		let files = [PathBuf::from("./files/config/validate/.codecrc.toml")];
+		let problems = validate(&files, None).unwrap_err().problems;
+
+		let problems = problems.iter().map(ToString::to_string).collect::<Vec<_>>();
+		assert_eq!(problems.len(), 3);
+		assert!(problems[0].starts_with("Unknown key 'profiles.work.author'"));
+		assert!(problems[1].starts_with("Unknown key 'template_path'"));
+		assert!(problems[2].contains("missing does not exist"));
+	}
+
+	#[test]
+	fn should_report_every_broken_file() {
+		fs::create_dir_all("./files/config/broken/package").unwrap();
+		fs::write(
+			"./files/config/broken/.codecrc.toml",
+			"templates_path = \"templates\"\n[variables]\na = 1",
+		)
+		.unwrap();
+		fs::write(
+			"./files/config/broken/package/.codecrc.toml",
+			"unset = \"a\"\ntemplate_path = \"templates\"",
+		)
+		.unwrap();
+
+		let files = [
+			PathBuf::from("./files/config/broken/.codecrc.toml"),
+			PathBuf::from("./files/config/broken/missing/.codecrc.toml"),
+			PathBuf::from("./files/config/broken/package/.codecrc.toml"),
+		];
+		let problems = validate(&files, None).unwrap_err().problems;
+
+		assert_eq!(problems.len(), 3);
+		assert!(problems[2]
+			.to_string()
+			.starts_with("Unknown key 'template_path'"));
+		assert!(problems[..2].iter().all(|problem| matches!(
+			problem,
+			ConfigProblem::Config(TomlConfigError::Unparseable { .. })
+		)));
+	}
+}
@@ -0,0 +1,216 @@
+use std::{
+	collections::HashMap,
+	fs,
+	path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::commands::generate::FilesGenerated;
+
+/// Path, relative to the working directory, where every `generate` run is recorded
+pub const HISTORY_PATH: &str = ".cco/history.toml";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct History {
+	#[serde(default)]
+	pub runs: Vec<Run>,
+}
+
+/// Everything a single `generate` run left on disk
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Run {
+	pub template: String,
+	pub variables: HashMap<String, String>,
+	pub output: PathBuf,
+	#[serde(default)]
+	pub files: Vec<FileRecord>,
+	/// Directories that did not exist before the run, in creation order
+	#[serde(default)]
+	pub directories: Vec<PathBuf>,
+	/// Existing files that were overwritten, appended or injected into, they can not be undone
+	#[serde(default)]
+	pub modified: Vec<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileRecord {
+	pub path: PathBuf,
+	/// Hash of the content as it was generated, see [`content_hash`]
+	pub hash: String,
+	/// Content as it was generated, the base to merge template updates
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub content: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum HistoryError {
+	#[error("Could not read generation history '{}'", .0.to_string_lossy())]
+	CouldNotRead(PathBuf),
+
+	#[error("Could not write generation history '{}'", .0.to_string_lossy())]
+	CouldNotWrite(PathBuf),
+
+	#[error("There is no generation to undo")]
+	#[diagnostic(code(history::Empty))]
+	Empty,
+
+	#[error("Generation history '{}' is unparseable: \n{source}", path.to_string_lossy())]
+	#[diagnostic(
+		code(history::Unparseable),
+		help("Remove the file to start a new history.")
+	)]
+	Unparseable {
+		path: PathBuf,
+		source: toml::de::Error,
+	},
+}
+
+impl History {
+	/// Reads the history at `path`, a missing file is an empty history
+	pub fn read(path: &Path) -> Result<Self, HistoryError> {
+		let content = match fs::read_to_string(path) {
+			Ok(content) => content,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+			Err(_) => return Err(HistoryError::CouldNotRead(path.to_owned())),
+		};
+
+		toml::from_str(&content).map_err(|source| HistoryError::Unparseable {
+			path: path.to_owned(),
+			source,
+		})
+	}
+
+	pub fn write(&self, path: &Path) -> Result<(), HistoryError> {
+		let content = match toml::to_string(self) {
+			Ok(content) => content,
+			Err(_) => return Err(HistoryError::CouldNotWrite(path.to_owned())),
+		};
+
+		if let Some(parent) = path.parent() {
+			if fs::create_dir_all(parent).is_err() {
+				return Err(HistoryError::CouldNotWrite(path.to_owned()));
+			}
+		}
+
+		fs::write(path, content).map_err(|_| HistoryError::CouldNotWrite(path.to_owned()))
+	}
+
+	/// Adds `run` at the end of the history stored at `path`
+	pub fn record(path: &Path, run: Run) -> Result<(), HistoryError> {
+		let mut history = Self::read(path)?;
+		history.runs.push(run);
+		history.write(path)
+	}
+}
+
+impl Run {
+	/// Records a generation, hashing its files as they are on disk right now
+	pub fn new(template: String, generated: &FilesGenerated) -> std::io::Result<Self> {
+		let files = generated
+			.created
+			.iter()
+			.map(|path| FileRecord::read(path))
+			.collect::<Result<_, _>>()?;
+
+		Ok(Self {
+			template,
+			variables: generated.variables.clone(),
+			output: absolute(&generated.output),
+			files,
+			directories: generated
+				.directories
+				.iter()
+				.map(|dir| absolute(dir))
+				.collect(),
+			modified: generated
+				.overwritten
+				.iter()
+				.chain(generated.appended.iter())
+				.chain(generated.injected.iter())
+				.map(|path| absolute(path))
+				.collect(),
+		})
+	}
+}
+
+impl FileRecord {
+	/// Records the current content of the file at `path`
+	pub fn read(path: &Path) -> std::io::Result<Self> {
+		let content = fs::read(path)?;
+
+		Ok(Self {
+			path: absolute(path),
+			hash: content_hash(&content),
+			content: String::from_utf8(content).ok(),
+		})
+	}
+
+	pub fn is_unmodified(&self) -> bool {
+		match fs::read(&self.path) {
+			Ok(content) => content_hash(&content) == self.hash,
+			Err(_) => false,
+		}
+	}
+}
+
+/// Hex encoded FNV-1a hash, stable across platforms and compiler versions
+pub fn content_hash(content: &[u8]) -> String {
+	const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+	const PRIME: u64 = 0x100000001b3;
+
+	let hash = content.iter().fold(OFFSET_BASIS, |hash, byte| {
+		(hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+	});
+
+	format!("{hash:016x}")
+}
+
+/// Records are read from any working directory, so paths are stored absolute. Paths that do not
+/// exist yet can not be canonicalized, they are only joined to the working directory.
+pub fn absolute(path: &Path) -> PathBuf {
+	// The current directory, like generations without an output directory
+	let path = match path.as_os_str().is_empty() {
+		true => Path::new("."),
+		false => path,
+	};
+
+	fs::canonicalize(path)
+		.or_else(|_| std::path::absolute(path))
+		.unwrap_or_else(|_| path.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_write_and_read_runs() {
+		let path = PathBuf::from("./files/history/history.toml");
+		let _ = fs::remove_file(&path);
+
+		let mut history = History::read(&path).unwrap();
+		assert!(history.runs.is_empty());
+
+		history.runs.push(Run {
+			template: "component".into(),
+			variables: HashMap::from([("name".into(), "Button".into())]),
+			output: PathBuf::from("src"),
+			files: vec![FileRecord {
+				path: PathBuf::from("src/Button.tsx"),
+				hash: content_hash(b"export const Button = () => null;"),
+				content: None,
+			}],
+			directories: vec![],
+			modified: vec![],
+		});
+		history.write(&path).unwrap();
+
+		let history = History::read(&path).unwrap();
+		assert_eq!(history.runs[0].variables["name"], "Button");
+		assert_eq!(
+			history.runs[0].files[0].hash,
+			content_hash(b"export const Button = () => null;")
+		);
+	}
+}
@@ -3,6 +3,7 @@ mod commands;
 mod config;
 mod diagnostics;
 mod dir_browser;
+mod history;
 mod template;
 mod terminal;
 mod traits;
@@ -11,15 +12,31 @@ mod io;
 
 use crate::cli::{Cli, Commands};
 use clap::Parser;
-use cli::GenerateCommand;
-use commands::{generate, list::list, path::print_path};
-use config::Config;
+use cli::{
+	ConfigCommand, ConfigCommands, GenerateCommand, InitCommand, InitLocation, InspectCommand,
+	LintCommand, NewTemplateCommand, UpdateCommand,
+};
+use commands::{
+	config::{print_config, print_sources, print_validation},
+	generate,
+	init::{init, InitError},
+	inspect::{inspect, print_inspection},
+	lint::lint,
+	list::list,
+	new_template::new_template,
+	path::print_path,
+	undo::undo,
+	update::update,
+};
+use config::{user_config_dir, Config};
+use history::{History, Run, HISTORY_PATH};
 use miette::IntoDiagnostic;
+use std::path::{Path, PathBuf};
 use terminal::create_file::create_file;
 
 fn main() -> miette::Result<()> {
-	let config = Config::try_new();
 	let cli = Cli::parse();
+	let config = Config::try_new(cli.config.clone(), cli.profile.clone());
 
 	match cli.commands {
 		Commands::List => {
@@ -27,23 +44,150 @@ fn main() -> miette::Result<()> {
 			list(&config).into_diagnostic()
 		}
 		Commands::Path => print_path(),
+		Commands::Config(ConfigCommand { command }) => match command {
+			ConfigCommands::Show => print_config(&config?),
+			ConfigCommands::Sources => {
+				print_sources(&config?);
+				Ok(())
+			}
+			ConfigCommands::Validate => print_validation(cli.config, cli.profile),
+		},
+		Commands::Inspect(InspectCommand { template }) => {
+			let config = config?;
+			let inspection = inspect(&template, &config)?;
+			print_inspection(&template, &inspection, &config);
+			Ok(())
+		}
+		Commands::Lint(LintCommand { template }) => {
+			let config = config?;
+			let linted = lint(template.as_deref(), &config)?;
+			println!("No problems found in {linted} templates");
+			Ok(())
+		}
+		Commands::NewTemplate(NewTemplateCommand {
+			template,
+			from,
+			name,
+		}) => {
+			let config = config?;
+			let scaffolded = new_template(&template, &from, &name, &config)?;
+			println!(
+				"Created template '{template}' in {}",
+				scaffolded.template_dir.to_string_lossy()
+			);
+			print_files("Wrote", &scaffolded.files);
+			if !scaffolded.skipped.is_empty() {
+				print_warnings(
+					"Skipped binary files, templates can only render text:",
+					&scaffolded.skipped,
+				);
+			}
+			Ok(())
+		}
+		Commands::Init(InitCommand {
+			location,
+			templates_path,
+			force,
+		}) => {
+			let dir = match location {
+				InitLocation::Project => std::env::current_dir().into_diagnostic()?,
+				InitLocation::User => user_config_dir().ok_or(InitError::UserDirNotFound)?,
+			};
+
+			let initialized = init(&dir, &templates_path, force)?;
+			println!("Wrote config {}", initialized.config.to_string_lossy());
+			match initialized.example {
+				Some(example) => println!("Created example template {}", example.to_string_lossy()),
+				None => println!(
+					"Kept templates directory {}",
+					initialized.templates_path.to_string_lossy()
+				),
+			}
+			Ok(())
+		}
+		Commands::Undo => {
+			let (run, undone) = undo(Path::new(HISTORY_PATH))?;
+			println!("Undid generation of '{}'", run.template);
+			print_files("Removed", &undone.removed);
+
+			if !undone.edited.is_empty() {
+				print_warnings("Kept, edited since generation:", &undone.edited);
+			}
+			if !undone.modified.is_empty() {
+				print_warnings(
+					"Existed before generation, revert by hand:",
+					&undone.modified,
+				);
+			}
+			if !undone.kept_directories.is_empty() {
+				print_warnings("Kept, not empty:", &undone.kept_directories);
+			}
+			Ok(())
+		}
+		Commands::Update(UpdateCommand { template }) => {
+			let config = config?;
+			let updated = update(template, Path::new(HISTORY_PATH), config)?;
+			println!("Updated generation of '{}'", updated.template);
+			print_files("Merged", &updated.merged);
+
+			if !updated.created.is_empty() {
+				print_files("Generated", &updated.created);
+			}
+			if !updated.conflicted.is_empty() {
+				print_warnings("Conflicts to solve by hand:", &updated.conflicted);
+			}
+			if !updated.stale.is_empty() {
+				print_warnings("Kept, not in the template anymore:", &updated.stale);
+			}
+			Ok(())
+		}
 		Commands::Generate(GenerateCommand {
 			template,
 			path,
+			name,
+			out_dir,
 			params,
+			dry_run,
+			allow_outside_output,
 		}) => {
 			let config = config?;
-			let output = match path {
-				None => create_file().into_diagnostic()?,
-				Some(path) => path.into(),
-			};
+			let output = output_path(path, name, out_dir).into_diagnostic()?;
 
-			match generate::generate(params, template, output, config) {
+			let options = generate::Options {
+				dry_run,
+				allow_outside_output,
+			};
+			match generate::generate(params, template.clone(), output, config, options) {
+				Ok(files) if dry_run => {
+					println!("Dry run, no file was written");
+					print_files("Would generate", &files.created);
+					if !files.overwritten.is_empty() {
+						print_files("Would overwrite", &files.overwritten);
+					}
+					if !files.appended.is_empty() {
+						print_files("Would append to", &files.appended);
+					}
+					if !files.injected.is_empty() {
+						print_files("Would inject into", &files.injected);
+					}
+					print_skipped(&files.skipped);
+					Ok(())
+				}
 				Ok(files) => {
-                    println!("Generated {} files:", files.len());
-					files.into_iter().for_each(|path| {
-						println!("{} ✓", path.to_string_lossy());
-					});
+					print_files("Generated", &files.created);
+					if !files.overwritten.is_empty() {
+						print_files("Overwrote", &files.overwritten);
+					}
+					if !files.appended.is_empty() {
+						print_files("Appended to", &files.appended);
+					}
+					if !files.injected.is_empty() {
+						print_files("Injected into", &files.injected);
+					}
+					print_skipped(&files.skipped);
+
+					let run = Run::new(template, &files).into_diagnostic()?;
+					History::record(Path::new(HISTORY_PATH), run)?;
 					Ok(())
 				}
 				Err(e) => Err(e.into()),
@@ -51,3 +195,47 @@ fn main() -> miette::Result<()> {
 		}
 	}
 }
+
+/// Joins the output directory and name of a generation, `path` is relative to `out_dir`. The
+/// output is picked interactively without both, clap rejects `out_dir` alone
+fn output_path(
+	path: Option<String>,
+	name: Option<String>,
+	out_dir: Option<String>,
+) -> std::io::Result<PathBuf> {
+	let out_dir = out_dir.map(PathBuf::from).unwrap_or_default();
+	match (path, name) {
+		(_, Some(name)) => Ok(out_dir.join(name)),
+		(Some(path), None) => Ok(out_dir.join(path)),
+		(None, None) => create_file(),
+	}
+}
+
+fn print_files(action: &str, files: &[PathBuf]) {
+	println!("{action} {} files:", files.len());
+	files.iter().for_each(|path| {
+		println!("{} ✓", path.to_string_lossy());
+	});
+}
+
+fn print_warnings(title: &str, files: &[PathBuf]) {
+	println!("{title}");
+	files.iter().for_each(|path| {
+		println!("{} ⚠", path.to_string_lossy());
+	});
+}
+
+fn print_skipped(skipped: &[generate::Skipped]) {
+	if skipped.is_empty() {
+		return;
+	}
+
+	println!("Skipped {} entries:", skipped.len());
+	skipped.iter().for_each(|skipped| {
+		println!(
+			"{} (when {})",
+			skipped.path.to_string_lossy(),
+			skipped.condition
+		);
+	});
+}
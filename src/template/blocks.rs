@@ -0,0 +1,216 @@
+use std::ops::Range;
+
+/// Blocks are named sections of a template file that a template extending it can override, like
+/// `{(#block imports)}import a;{(/block)}`
+pub const BLOCK_START: &str = "{(#block ";
+pub const BLOCK_END: &str = "{(/block)}";
+const TAG_END: &str = ")}";
+
+#[derive(Debug)]
+pub struct Block<'a> {
+	pub name: &'a str,
+	/// Range of the content, without the tags
+	pub content: Range<usize>,
+	/// Range of the whole block, including the tags
+	pub span: Range<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct BlockError {
+	pub reason: &'static str,
+	pub span: Range<usize>,
+}
+
+impl BlockError {
+	fn new(reason: &'static str, span: Range<usize>) -> Self {
+		Self { reason, span }
+	}
+}
+
+/// Finds every block of `source`. A newline right after a tag is part of the tag, so tags can be
+/// written in their own lines.
+pub fn find_blocks(source: &str) -> Result<Vec<Block<'_>>, BlockError> {
+	let mut blocks = vec![];
+	let mut i = 0;
+
+	while let Some(found) = source[i..].find(BLOCK_START) {
+		let start = i + found;
+		let name_start = start + BLOCK_START.len();
+		let name_end = match source[name_start..].find(TAG_END) {
+			Some(len) => name_start + len,
+			None => {
+				return Err(BlockError::new(
+					"block tag is not closed",
+					start..name_start,
+				))
+			}
+		};
+
+		let name = source[name_start..name_end].trim();
+		if name.is_empty() {
+			let span = start..name_end + TAG_END.len();
+			return Err(BlockError::new("block has no name", span));
+		}
+
+		let content_start = skip_newline(source, name_end + TAG_END.len());
+		let content_end = match source[content_start..].find(BLOCK_END) {
+			Some(len) => content_start + len,
+			None => {
+				let span = start..name_end + TAG_END.len();
+				return Err(BlockError::new("block is never ended", span));
+			}
+		};
+
+		if let Some(nested) = source[content_start..content_end].find(BLOCK_START) {
+			let nested = content_start + nested;
+			let span = nested..nested + BLOCK_START.len();
+			return Err(BlockError::new("blocks can not be nested", span));
+		}
+
+		let end = skip_newline(source, content_end + BLOCK_END.len());
+		blocks.push(Block {
+			name,
+			content: content_start..content_end,
+			span: start..end,
+		});
+
+		i = end;
+	}
+
+	Ok(blocks)
+}
+
+/// Applies a file of an extending template over the same file of its base: if `child` defines
+/// blocks, they replace the blocks of `base` with the same name, otherwise `child` replaces the
+/// whole file. Tags are kept so further extensions can override them too.
+///
+/// A `child` with blocks must only have blocks of `base`, anything else would be dropped.
+pub fn extend(base: &str, child: &str) -> Result<String, BlockError> {
+	let overrides = find_blocks(child)?;
+	if overrides.is_empty() {
+		return Ok(child.to_owned());
+	}
+
+	let blocks = find_blocks(base)?;
+	check_overrides(child, &overrides, &blocks)?;
+
+	let mut extended = String::with_capacity(base.len());
+	let mut i = 0;
+
+	for block in blocks {
+		let content = overrides
+			.iter()
+			.find(|child_block| child_block.name == block.name)
+			.map(|child_block| &child[child_block.content.clone()])
+			.unwrap_or(&base[block.content.clone()]);
+
+		extended.push_str(&base[i..block.content.start]);
+		extended.push_str(content);
+		i = block.content.end;
+	}
+
+	extended.push_str(&base[i..]);
+	Ok(extended)
+}
+
+const OUTSIDE_BLOCKS: &str = "text outside of blocks is not in the base";
+
+fn check_overrides(child: &str, overrides: &[Block], blocks: &[Block]) -> Result<(), BlockError> {
+	let mut i = 0;
+
+	for child_block in overrides {
+		if let Some(span) = text_span(child, i..child_block.span.start) {
+			return Err(BlockError::new(OUTSIDE_BLOCKS, span));
+		}
+		i = child_block.span.end;
+
+		if blocks.iter().all(|block| block.name != child_block.name) {
+			let tag = child_block.span.start..child_block.content.start;
+			let span = text_span(child, tag.clone()).unwrap_or(tag);
+			return Err(BlockError::new("block is not in the base", span));
+		}
+	}
+
+	match text_span(child, i..child.len()) {
+		Some(span) => Err(BlockError::new(OUTSIDE_BLOCKS, span)),
+		None => Ok(()),
+	}
+}
+
+/// Range of `range` without its surrounding whitespace, `None` if there is only whitespace
+fn text_span(source: &str, range: Range<usize>) -> Option<Range<usize>> {
+	let text = &source[range.clone()];
+	let start = range.start + text.len() - text.trim_start().len();
+	let end = range.start + text.trim_end().len();
+
+	(start < end).then_some(start..end)
+}
+
+/// Replaces every block of `source` by its content
+pub fn strip_blocks(source: &str) -> Result<String, BlockError> {
+	let mut stripped = String::with_capacity(source.len());
+	let mut i = 0;
+
+	for block in find_blocks(source)? {
+		stripped.push_str(&source[i..block.span.start]);
+		stripped.push_str(&source[block.content]);
+		i = block.span.end;
+	}
+
+	stripped.push_str(&source[i..]);
+	Ok(stripped)
+}
+
+fn skip_newline(source: &str, at: usize) -> usize {
+	if source[at..].starts_with('\n') {
+		at + 1
+	} else {
+		at
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_override_blocks_by_name() {
+		let base = "{(#block imports)}\nimport a;\n{(/block)}\nclass {(name)} {}\n";
+		let child = "{(#block imports)}\nimport b;\n{(/block)}\n";
+
+		let extended = extend(base, child).unwrap();
+		assert_eq!(
+			strip_blocks(&extended).unwrap(),
+			"import b;\nclass {(name)} {}\n"
+		);
+		assert_eq!(
+			strip_blocks(base).unwrap(),
+			"import a;\nclass {(name)} {}\n"
+		);
+	}
+
+	#[test]
+	fn should_replace_files_without_blocks() {
+		assert_eq!(extend("{(#block a)}a{(/block)}", "b").unwrap(), "b");
+	}
+
+	#[test]
+	fn should_reject_what_the_base_does_not_have() {
+		let base = "{(#block imports)}\nimport a;\n{(/block)}\n";
+
+		let error = extend(base, "{(#block import)}\nimport b;\n{(/block)}\n").unwrap_err();
+		assert_eq!(error, BlockError::new("block is not in the base", 0..17));
+
+		let error = extend(base, "{(#block imports)}{(/block)}\nclass A {}\n").unwrap_err();
+		assert_eq!(error, BlockError::new(OUTSIDE_BLOCKS, 29..39));
+
+		let error = extend("import a;\n", "{(#block imports)}{(/block)}").unwrap_err();
+		assert_eq!(error, BlockError::new("block is not in the base", 0..18));
+	}
+
+	#[test]
+	fn should_reject_unended_blocks() {
+		let error = find_blocks("{(#block imports)}\nimport a;\n").unwrap_err();
+		assert_eq!(error, BlockError::new("block is never ended", 0..18));
+	}
+}
@@ -0,0 +1,166 @@
+use std::{collections::HashMap, fmt::Display};
+
+/// Values of a variable that make a condition on it false
+const FALSY: [&str; 5] = ["", "false", "0", "no", "off"];
+
+/// A condition on the variables of a generation, like `with_tests`, `!with_tests`,
+/// `style == scss` or `style != css`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+	/// The variable is set to a value that is not falsy, see [`FALSY`]
+	Set(String),
+	NotSet(String),
+	Equals(String, String),
+	NotEquals(String, String),
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+#[error("Condition '{condition}' is invalid")]
+#[diagnostic(
+	code(template_error::ConditionInvalid),
+	help("Conditions look like `var`, `!var`, `var == value` or `var != value`.")
+)]
+pub struct ConditionError {
+	pub condition: String,
+}
+
+impl Condition {
+	pub fn parse(condition: &str) -> Result<Self, ConditionError> {
+		let invalid = || ConditionError {
+			condition: condition.to_owned(),
+		};
+
+		let parsed = if let Some((var, value)) = condition.split_once("!=") {
+			Condition::NotEquals(variable(var).ok_or_else(invalid)?, unquote(value))
+		} else if let Some((var, value)) = condition.split_once("==") {
+			Condition::Equals(variable(var).ok_or_else(invalid)?, unquote(value))
+		} else if let Some(var) = condition.trim().strip_prefix('!') {
+			Condition::NotSet(variable(var).ok_or_else(invalid)?)
+		} else {
+			Condition::Set(variable(condition).ok_or_else(invalid)?)
+		};
+
+		Ok(parsed)
+	}
+
+	/// Variable the condition is on
+	pub fn variable(&self) -> &str {
+		match self {
+			Condition::Set(var)
+			| Condition::NotSet(var)
+			| Condition::Equals(var, _)
+			| Condition::NotEquals(var, _) => var,
+		}
+	}
+
+	pub fn evaluate(&self, vars: &HashMap<String, String>) -> bool {
+		match self {
+			Condition::Set(var) => is_set(vars.get(var)),
+			Condition::NotSet(var) => !is_set(vars.get(var)),
+			Condition::Equals(var, value) => vars.get(var) == Some(value),
+			Condition::NotEquals(var, value) => vars.get(var) != Some(value),
+		}
+	}
+
+	/// Whether this condition and `other` can never hold together, like `with_tests` and
+	/// `!with_tests`, or `style == scss` and `style == css`
+	pub fn contradicts(&self, other: &Condition) -> bool {
+		match (self, other) {
+			(Condition::Set(var), Condition::NotSet(other_var))
+			| (Condition::NotSet(var), Condition::Set(other_var)) => var == other_var,
+			(Condition::Equals(var, value), Condition::Equals(other_var, other_value)) => {
+				var == other_var && value != other_value
+			}
+			(Condition::Equals(var, value), Condition::NotEquals(other_var, other_value))
+			| (Condition::NotEquals(other_var, other_value), Condition::Equals(var, value)) => {
+				var == other_var && value == other_value
+			}
+			(Condition::Equals(var, value), Condition::Set(other_var))
+			| (Condition::Set(other_var), Condition::Equals(var, value)) => {
+				var == other_var && !is_set(Some(value))
+			}
+			(Condition::Equals(var, value), Condition::NotSet(other_var))
+			| (Condition::NotSet(other_var), Condition::Equals(var, value)) => {
+				var == other_var && is_set(Some(value))
+			}
+			_ => false,
+		}
+	}
+}
+
+impl Display for Condition {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Condition::Set(var) => write!(f, "{var}"),
+			Condition::NotSet(var) => write!(f, "!{var}"),
+			Condition::Equals(var, value) => write!(f, "{var} == {value}"),
+			Condition::NotEquals(var, value) => write!(f, "{var} != {value}"),
+		}
+	}
+}
+
+fn is_set(value: Option<&String>) -> bool {
+	match value {
+		Some(value) => !FALSY.contains(&value.trim().to_lowercase().as_str()),
+		None => false,
+	}
+}
+
+fn variable(var: &str) -> Option<String> {
+	let var = var.trim();
+	if var.is_empty() || var.contains(char::is_whitespace) {
+		return None;
+	}
+
+	Some(var.to_owned())
+}
+
+fn unquote(value: &str) -> String {
+	let value = value.trim();
+	value
+		.strip_prefix('"')
+		.and_then(|value| value.strip_suffix('"'))
+		.unwrap_or(value)
+		.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_evaluate_conditions() {
+		let vars = HashMap::from([
+			("with_tests".to_owned(), "true".to_owned()),
+			("with_stories".to_owned(), "false".to_owned()),
+			("style".to_owned(), "scss".to_owned()),
+		]);
+
+		let holds = |condition: &str| Condition::parse(condition).unwrap().evaluate(&vars);
+		assert!(holds("with_tests"));
+		assert!(!holds("with_stories"));
+		assert!(holds("!missing"));
+		assert!(holds("style == \"scss\""));
+		assert!(holds("style != css"));
+	}
+
+	#[test]
+	fn should_find_contradictions() {
+		let contradicts = |condition: &str, other: &str| {
+			let condition = Condition::parse(condition).unwrap();
+			condition.contradicts(&Condition::parse(other).unwrap())
+		};
+		assert!(contradicts("with_tests", "!with_tests"));
+		assert!(contradicts("style == scss", "style == css"));
+		assert!(contradicts("style != css", "style == css"));
+		assert!(contradicts("!style", "style == css"));
+		assert!(!contradicts("style != scss", "style == css"));
+		assert!(!contradicts("with_tests", "with_stories"));
+	}
+
+	#[test]
+	fn should_reject_invalid_conditions() {
+		assert!(Condition::parse("with tests").is_err());
+		assert!(Condition::parse("== scss").is_err());
+	}
+}
@@ -0,0 +1,358 @@
+use std::{
+	collections::HashMap,
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use super::condition::{Condition, ConditionError};
+
+/// Name of the optional file, at the root of a template, that describes how the template is
+/// generated. It is never copied to the output.
+pub const MANIFEST_FILENAME: &str = ".template.toml";
+
+/// Template files ending with this suffix are appended to their output, the suffix is removed
+/// from the output filename
+pub const APPEND_SUFFIX: &str = ".append";
+
+/// Template entries whose filename ends with `[if <condition>]` are only generated when the
+/// condition holds, like `{(name)}.spec.ts[if with_tests]`
+pub const CONDITION_START: &str = "[if ";
+pub const CONDITION_END: char = ']';
+
+/// Keys of a manifest, see [`Manifest`]
+pub const MANIFEST_KEYS: [&str; 5] = ["extends", "compose", "inject", "files", "root"];
+/// Keys of each `files.<path>` table, see [`FileRule`]
+pub const FILE_RULE_KEYS: [&str; 2] = ["mode", "when"];
+/// Keys of each `[[inject]]` table, see [`Injection`]
+pub const INJECTION_KEYS: [&str; 5] = ["target", "content", "anchor", "pattern", "position"];
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Manifest {
+	/// Template this one is based on. Entries of this template are added to the base, or replace
+	/// the ones with the same path. Files that define blocks only override those blocks of the
+	/// base file.
+	#[serde(default)]
+	pub extends: Option<String>,
+	/// Other templates generated along with this one, with the same variables
+	#[serde(default)]
+	pub compose: Vec<String>,
+	/// Snippets injected into files that already exist, like an `index.ts` or a `mod.rs`
+	#[serde(default)]
+	pub inject: Vec<Injection>,
+	/// Settings of single entries, keyed by their path relative to the template root, like
+	/// `"docs/CHANGELOG.md"`
+	#[serde(default)]
+	pub files: HashMap<String, FileRule>,
+	/// Where the template root is generated, see [`RootLayout`]
+	#[serde(default)]
+	pub root: Option<RootLayout>,
+}
+
+/// Where the entries at the root of a template are generated, for an output like `src/Button`
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RootLayout {
+	/// Next to the output name, in `src/`
+	#[default]
+	Parent,
+	/// In a new directory named after the output name, `src/Button/`
+	Directory,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct FileRule {
+	#[serde(default)]
+	pub mode: WriteMode,
+	/// Condition to generate the entry, see [`Condition`]
+	#[serde(default)]
+	pub when: Option<String>,
+}
+
+/// Settings of a template entry, from its filename and the manifest
+#[derive(Debug, PartialEq, Eq)]
+pub struct EntrySettings<'a> {
+	/// Filename without the settings suffixes
+	pub filename: &'a str,
+	pub mode: WriteMode,
+	pub condition: Option<Condition>,
+}
+
+/// How a rendered file is written when its output already exists
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WriteMode {
+	#[default]
+	Overwrite,
+	Append,
+}
+
+/// A snippet to inject into an existing file
+/// Atributes
+/// * `target`: templated path of the file, relative to the output directory
+/// * `content`: templated snippet to insert
+/// * `anchor`: the snippet is inserted next to the first line containing this marker
+/// * `pattern`: like `anchor`, but the line is matched with a regex
+/// * `position`: where to insert the snippet, relative to the anchor or to the whole file
+#[derive(Deserialize, Debug, Clone)]
+pub struct Injection {
+	pub target: String,
+	pub content: String,
+	#[serde(default)]
+	pub anchor: Option<String>,
+	#[serde(default)]
+	pub pattern: Option<String>,
+	#[serde(default)]
+	pub position: Option<Position>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Position {
+	Before,
+	After,
+	Append,
+	Prepend,
+}
+
+impl Injection {
+	/// Without an explicit position, anchored snippets go after the anchor and the rest are
+	/// appended
+	pub fn position(&self) -> Position {
+		match self.position {
+			Some(position) => position,
+			None if self.anchor.is_some() || self.pattern.is_some() => Position::After,
+			None => Position::Append,
+		}
+	}
+}
+
+impl std::fmt::Display for Position {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let name = match self {
+			Position::Before => "before",
+			Position::After => "after",
+			Position::Append => "append",
+			Position::Prepend => "prepend",
+		};
+
+		f.write_str(name)
+	}
+}
+
+/// A templated string of a manifest, like the target of an injection
+#[derive(Debug, PartialEq, Eq)]
+pub struct ManifestString {
+	pub value: String,
+	/// Offset of the string in the manifest, of its first character when `verbatim`, or of its
+	/// opening quote otherwise
+	pub offset: usize,
+	/// Whether `value` is written as it is, so offsets in `value` are offsets in the manifest too.
+	/// Escapes, like `\n`, make them differ.
+	pub verbatim: bool,
+}
+
+/// Injections of a manifest, with the spans their strings are written at
+#[derive(Deserialize)]
+struct SpannedManifest {
+	#[serde(default)]
+	inject: Vec<SpannedInjection>,
+}
+
+#[derive(Deserialize)]
+struct SpannedInjection {
+	target: Spanned<String>,
+	content: Spanned<String>,
+}
+
+/// The templated strings of the injections of the manifest `content`, empty if it is
+/// unparseable
+pub fn injection_strings(content: &str) -> Vec<ManifestString> {
+	let injections = match toml::from_str::<SpannedManifest>(content) {
+		Ok(manifest) => manifest.inject,
+		Err(_) => return vec![],
+	};
+
+	injections
+		.into_iter()
+		.flat_map(|injection| [injection.target, injection.content])
+		.map(|spanned| {
+			let span = spanned.span();
+			let written = &content[span.clone()];
+			let value = spanned.into_inner();
+			let quote = ["\"\"\"", "'''", "\"", "'"]
+				.into_iter()
+				.find(|quote| written.starts_with(quote))
+				.unwrap_or_default();
+
+			let unquoted = written.get(quote.len()..written.len() - quote.len());
+			let verbatim = unquoted == Some(value.as_str());
+			ManifestString {
+				offset: span.start + if verbatim { quote.len() } else { 0 },
+				value,
+				verbatim,
+			}
+		})
+		.collect()
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum ManifestError {
+	#[error("Could not read template manifest '{}'", .0.to_string_lossy())]
+	CouldNotRead(PathBuf),
+
+	#[error("Template manifest '{}' is unparseable: \n{source}", path.to_string_lossy())]
+	#[diagnostic(code(template_error::ManifestUnparseable))]
+	Unparseable {
+		path: PathBuf,
+		source: toml::de::Error,
+	},
+}
+
+impl Manifest {
+	/// Reads the manifest of the template at `template_dir`, templates without one get the default
+	/// manifest
+	pub fn from_template_dir(template_dir: &Path) -> Result<Self, ManifestError> {
+		let path = template_dir.join(MANIFEST_FILENAME);
+		let content = match fs::read_to_string(&path) {
+			Ok(content) => content,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+			Err(_) => return Err(ManifestError::CouldNotRead(path)),
+		};
+
+		toml::from_str(&content).map_err(|source| ManifestError::Unparseable { path, source })
+	}
+
+	/// Merges the manifest of a template extending this one, `child` settings win
+	pub fn extended_by(mut self, child: Manifest) -> Manifest {
+		self.extends = child.extends;
+		self.compose.extend(child.compose);
+		self.inject.extend(child.inject);
+		self.files.extend(child.files);
+		self.root = child.root.or(self.root);
+
+		self
+	}
+
+	/// Reads the settings of a template entry, suffixes of the filename win over the manifest
+	/// Arguments:
+	/// * `relative_path`: path of the entry relative to the template root
+	/// * `filename`: filename of the entry
+	pub fn entry_settings<'a>(
+		&self,
+		relative_path: &Path,
+		filename: &'a str,
+	) -> Result<EntrySettings<'a>, ConditionError> {
+		let rule = self.file_rule(relative_path);
+		let mut settings = EntrySettings {
+			filename,
+			mode: rule.map(|rule| rule.mode).unwrap_or_default(),
+			condition: None,
+		};
+
+		if let Some(when) = rule.and_then(|rule| rule.when.as_ref()) {
+			settings.condition = Some(Condition::parse(when)?);
+		}
+
+		let suffixed_condition = settings
+			.filename
+			.strip_suffix(CONDITION_END)
+			.and_then(|filename| filename.rsplit_once(CONDITION_START));
+		if let Some((filename, condition)) = suffixed_condition {
+			settings.filename = filename;
+			settings.condition = Some(Condition::parse(condition)?);
+		}
+
+		if let Some(filename) = settings.filename.strip_suffix(APPEND_SUFFIX) {
+			settings.filename = filename;
+			settings.mode = WriteMode::Append;
+		}
+
+		Ok(settings)
+	}
+
+	fn file_rule(&self, relative_path: &Path) -> Option<&FileRule> {
+		let key = relative_path
+			.components()
+			.map(|component| component.as_os_str().to_string_lossy())
+			.collect::<Vec<_>>()
+			.join("/");
+
+		self.files.get(&key)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_default_position_from_anchor() {
+		let manifest: Manifest = toml::from_str(
+			r#"
+			[[inject]]
+			target = "mod.rs"
+			content = "mod {(name)};"
+			anchor = "// modules"
+
+			[[inject]]
+			target = "index.ts"
+			content = "export * from './{(name)}';"
+			"#,
+		)
+		.unwrap();
+
+		assert_eq!(manifest.inject[0].position(), Position::After);
+		assert_eq!(manifest.inject[1].position(), Position::Append);
+	}
+
+	#[test]
+	fn should_read_entry_settings() {
+		let manifest: Manifest = toml::from_str(
+			r#"
+			[files."docs/CHANGELOG.md"]
+			mode = "append"
+
+			[files."{(name)}.spec.ts"]
+			when = "with_tests"
+			"#,
+		)
+		.unwrap();
+
+		let settings = |path: &'static str| {
+			let path = Path::new(path);
+			let filename = path.file_name().unwrap().to_str().unwrap();
+			manifest.entry_settings(path, filename).unwrap()
+		};
+
+		assert_eq!(settings("docs/CHANGELOG.md").mode, WriteMode::Append);
+		assert_eq!(settings("README.md").mode, WriteMode::Overwrite);
+		assert_eq!(
+			settings("{(name)}.spec.ts").condition,
+			Some(Condition::Set("with_tests".into()))
+		);
+		assert_eq!(
+			settings("styles.scss.append[if style == scss]"),
+			EntrySettings {
+				filename: "styles.scss",
+				mode: WriteMode::Append,
+				condition: Some(Condition::Equals("style".into(), "scss".into())),
+			}
+		);
+	}
+
+	#[test]
+	fn should_find_injection_strings() {
+		let content = "[[inject]]\ntarget = 'mod.rs'\ncontent = \"mod {(name)};\\n\"\n";
+		let strings = injection_strings(content);
+
+		assert_eq!(strings[0].value, "mod.rs");
+		assert_eq!(&content[strings[0].offset..][..6], "mod.rs");
+		assert!(strings[0].verbatim);
+		assert_eq!(strings[1].value, "mod {(name)};\n");
+		assert_eq!(&content[strings[1].offset..][..5], "\"mod ");
+		assert!(!strings[1].verbatim);
+	}
+}
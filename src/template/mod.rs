@@ -1,2 +1,5 @@
+pub mod blocks;
+pub mod condition;
+pub mod manifest;
 pub mod parse;
 mod pipes;
\ No newline at end of file
@@ -1,16 +1,22 @@
 pub mod error;
 
 use std::{
+	fs,
 	io::Write,
 	iter::{Enumerate, Skip},
+	path::PathBuf,
 	str::Chars,
 };
 
 use std::collections::HashMap;
 
-use self::error::{Error, ParamNotFound, PipeUndefined, InternalError};
+use self::error::{
+	Error, IncludeCycle, InternalError, ParamNotFound, PartialInvalid, PartialNotFound, PipeUndefined,
+};
 
-use super::pipes::{capitalize_all, capitalize_once};
+use super::pipes::{
+	camel_case, capitalize_all, capitalize_once, constant_case, kebab_case, pascal_case, snake_case,
+};
 
 type Pipe = fn(&str) -> String;
 type PipesMap = HashMap<&'static str, Pipe>;
@@ -20,11 +26,29 @@ pub trait TemplateParse {
 	/// Note that this method may write a lot of times, so ensure to use a BufWrite if working with
 	/// blocking systems.
 	fn parse<W: Write>(&self, content: &str, writter: &mut W) -> Result<(), Error>;
+
+	/// Variables available to the template
+	fn vars(&self) -> &HashMap<String, String>;
 }
 
+/// Pipes converting a name to each case style, like `{(name|kebab_case)}`
+pub const CASE_PIPES: [(&str, Pipe); 5] = [
+	("pascal_case", pascal_case),
+	("camel_case", camel_case),
+	("kebab_case", kebab_case),
+	("snake_case", snake_case),
+	("constant_case", constant_case),
+];
+
+/// Directory, under the templates path, of the partials included with `{(> partial)}`
+pub const PARTIALS_DIR: &str = "_partials";
+/// Starts the name of a partial, like `{(> header)}`
+pub const INCLUDE: char = '>';
+
 pub struct DefaultTemplateParse {
 	pipes: PipesMap,
 	vars: HashMap<String, String>,
+	partials: Vec<PathBuf>,
 }
 
 impl DefaultTemplateParse {
@@ -32,20 +56,73 @@ impl DefaultTemplateParse {
 		let mut pipes: PipesMap = HashMap::new();
 		pipes.insert("capitalize_once", capitalize_once);
 		pipes.insert("capitalize_all", |slice| capitalize_all(slice, '-'));
+		for (name, pipe) in CASE_PIPES {
+			pipes.insert(name, pipe);
+		}
 
-		Self { pipes, vars }
+		Self {
+			pipes,
+			vars,
+			partials: vec![],
+		}
 	}
-}
 
-impl TemplateParse for DefaultTemplateParse {
-	fn parse<W: Write>(&self, content: &str, writter: &mut W) -> Result<(), Error> {
+	/// Allows including the partials of the `_partials` directory under each templates path, the
+	/// first ones shadow partials with the same name in the next ones
+	pub fn with_partials(mut self, templates_paths: &[PathBuf]) -> Self {
+		self.partials = templates_paths
+			.iter()
+			.map(|templates_path| templates_path.join(PARTIALS_DIR))
+			.collect();
+		self
+	}
+
+	pub fn has_pipe(&self, pipe: &str) -> bool {
+		self.pipes.contains_key(pipe)
+	}
+
+	/// Names of the available pipes, sorted
+	pub fn pipes(&self) -> Vec<&'static str> {
+		let mut pipes = self.pipes.keys().copied().collect::<Vec<_>>();
+		pipes.sort();
+		pipes
+	}
+
+	/// Path and content of the partial `name`, from the first partials directory that has it
+	pub fn read_partial(&self, name: &str) -> Option<(PathBuf, String)> {
+		self.partials.iter().find_map(|partials| {
+			let path = partials.join(name);
+			let content = fs::read_to_string(&path).ok()?;
+			Some((path, content))
+		})
+	}
+
+	/// Arguments:
+	/// * `including`: partials being included, from the outermost one
+	fn parse_including<W: Write>(
+		&self,
+		content: &str,
+		writter: &mut W,
+		including: &mut Vec<String>,
+	) -> Result<(), Error> {
 		let mut i = 0usize;
+		// Tags after a broken one are still parsed, to report every error at once
+		let mut errors = vec![];
 
 		for TemplateParam { name, start, end } in ParamsBrowser::new(content) {
 			/* Previous slice */
 			let back = &content[i..start];
 
-			writter.write(back.as_bytes())?;
+			writter.write_all(back.as_bytes())?;
+			i = end;
+
+			if let Some(partial) = name.strip_prefix(INCLUDE) {
+				match self.include(partial.trim(), (start, end), writter, including) {
+					Err(Error::Internal(error)) => errors.push(error),
+					included => included?,
+				}
+				continue;
+			}
 
 			let (var_name, pipes_iter) = {
 				let mut var_slices = name.split(SEPARATOR);
@@ -56,23 +133,87 @@ impl TemplateParse for DefaultTemplateParse {
 				(var_name, pipes)
 			};
 
-			let value = self
-				.vars
-				.get(var_name)
-				.ok_or_else(|| ParamNotFound { end, start })?;
-
-			let piped_value = apply_pipes(value, pipes_iter, &self.pipes)?;
-			writter.write(piped_value.as_bytes())?;
+			let Some(value) = self.vars.get(var_name) else {
+				errors.push(ParamNotFound { end, start }.into());
+				continue;
+			};
 
-			i = end;
+			match apply_pipes(value, pipes_iter, &self.pipes) {
+				Ok(piped_value) => writter.write_all(piped_value.as_bytes())?,
+				Err(mut undefined) => {
+					// Point at the pipe, inside of the tag
+					let pipe = format!("{SEPARATOR}{}", undefined.pipename);
+					let at = name.find(&pipe).unwrap_or(0) + SEPARATOR.len_utf8();
+					undefined.slice = (start + LEFT_LEN + at, undefined.pipename.len());
+					errors.push(undefined.into());
+				}
+			}
 		}
 
-		writter.write(content[i..].as_bytes())?;
+		writter.write_all(&content.as_bytes()[i..])?;
+		InternalError::collect(errors)?;
 		Ok(())
 	}
+
+	fn include<W: Write>(
+		&self,
+		name: &str,
+		(start, end): (usize, usize),
+		writter: &mut W,
+		including: &mut Vec<String>,
+	) -> Result<(), Error> {
+		if including.iter().any(|included| included == name) {
+			let mut chain = including.clone();
+			chain.push(name.to_owned());
+
+			return Err(
+				IncludeCycle {
+					chain: chain.join(" -> "),
+					start,
+					end,
+				}
+				.into(),
+			);
+		}
+
+		let (_, content) = self.read_partial(name).ok_or_else(|| PartialNotFound {
+			name: name.to_owned(),
+			start,
+			end,
+		})?;
+
+		including.push(name.to_owned());
+		let included = self.parse_including(&content, writter, including);
+		including.pop();
+
+		match included {
+			Err(Error::Internal(error)) => Err(
+				PartialInvalid {
+					name: name.to_owned(),
+					content,
+					start,
+					end,
+					error: Box::new(error),
+				}
+				.into(),
+			),
+			included => included,
+		}
+	}
+}
+
+impl TemplateParse for DefaultTemplateParse {
+	fn parse<W: Write>(&self, content: &str, writter: &mut W) -> Result<(), Error> {
+		self.parse_including(content, writter, &mut vec![])
+	}
+
+	fn vars(&self) -> &HashMap<String, String> {
+		&self.vars
+	}
 }
 
-const SEPARATOR: char = '|';
+/// Separates a variable from its pipes, like `{(name|kebab_case)}`
+pub const SEPARATOR: char = '|';
 
 fn _parse(
 	template: &str,
@@ -97,9 +238,7 @@ fn _parse(
 			(var_name, pipes)
 		};
 
-		let value = params
-			.get(var_name)
-			.ok_or_else(|| ParamNotFound { end, start })?;
+		let value = params.get(var_name).ok_or(ParamNotFound { end, start })?;
 
 		let piped_value = apply_pipes(value, pipes_iter, &pipes)?;
 		parsed.push_str(&piped_value);
@@ -112,13 +251,15 @@ fn _parse(
 	Ok(parsed)
 }
 
-static LEFT: &str = "{(";
+/// Opens an expression, like `{(name)}`
+pub static LEFT: &str = "{(";
 static RIGHT: &str = ")}";
 
 static LEFT_LEN: usize = LEFT.len();
 static RIGHT_LEN: usize = RIGHT.len();
 
-struct ParamsBrowser<'a> {
+/// Iterates over the `{(...)}` expressions of a template
+pub struct ParamsBrowser<'a> {
 	template: &'a str,
 	name_at: Option<usize>,
 	iter: Skip<Enumerate<Chars<'a>>>,
@@ -136,10 +277,11 @@ impl<'a> ParamsBrowser<'a> {
 	}
 }
 
-struct TemplateParam<'a> {
-	name: &'a str,
-	start: usize,
-	end: usize,
+pub struct TemplateParam<'a> {
+	/// Everything between the delimiters, like `name|kebab_case`
+	pub name: &'a str,
+	pub start: usize,
+	pub end: usize,
 }
 
 impl<'a> Iterator for ParamsBrowser<'a> {
@@ -7,6 +7,30 @@ pub enum Error {
 pub enum InternalError {
 	ParamNotFound(ParamNotFound),
 	PipeNotFound(PipeUndefined),
+	PartialNotFound(PartialNotFound),
+	IncludeCycle(IncludeCycle),
+	PartialInvalid(PartialInvalid),
+	/// Every error of a content, when there are many of them
+	Many(Vec<InternalError>),
+}
+
+impl InternalError {
+	/// Fails with every error of `errors`, nested bundles of errors are flattened
+	pub fn collect(errors: Vec<InternalError>) -> Result<(), Self> {
+		let mut flattened = vec![];
+		for error in errors {
+			match error {
+				Self::Many(errors) => flattened.extend(errors),
+				error => flattened.push(error),
+			}
+		}
+
+		match flattened.len() {
+			0 => Ok(()),
+			1 => Err(flattened.remove(0)),
+			_ => Err(Self::Many(flattened)),
+		}
+	}
 }
 
 #[derive(Debug)]
@@ -21,6 +45,33 @@ pub struct PipeUndefined {
 	pub slice: (usize, usize),
 }
 
+#[derive(Debug)]
+pub struct PartialNotFound {
+	pub name: String,
+	pub start: usize,
+	pub end: usize,
+}
+
+#[derive(Debug)]
+pub struct IncludeCycle {
+	pub chain: String,
+	pub start: usize,
+	pub end: usize,
+}
+
+/// An error inside of an included partial
+/// Atributes
+/// * `content`: content of the partial, where `error` happened
+/// * `start`, `end`: position of the include in the including content
+#[derive(Debug)]
+pub struct PartialInvalid {
+	pub name: String,
+	pub content: String,
+	pub start: usize,
+	pub end: usize,
+	pub error: Box<InternalError>,
+}
+
 impl PipeUndefined {
 	pub fn new(pipename: String, slice: (usize, usize)) -> Self {
 		Self { pipename, slice }
@@ -62,3 +113,21 @@ impl From<ParamNotFound> for InternalError {
 		InternalError::ParamNotFound(err)
 	}
 }
+
+impl From<PartialNotFound> for Error {
+	fn from(err: PartialNotFound) -> Self {
+		Self::Internal(InternalError::PartialNotFound(err))
+	}
+}
+
+impl From<IncludeCycle> for Error {
+	fn from(err: IncludeCycle) -> Self {
+		Self::Internal(InternalError::IncludeCycle(err))
+	}
+}
+
+impl From<PartialInvalid> for Error {
+	fn from(err: PartialInvalid) -> Self {
+		Self::Internal(InternalError::PartialInvalid(err))
+	}
+}
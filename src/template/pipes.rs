@@ -39,3 +39,83 @@ pub fn capitalize_once(slice: &str) -> String {
 pub fn capitalize_all(slice: &str, separator: char) -> String {
 	slice.split(separator).map(capitalize_once).collect()
 }
+
+/// Splits a name in any case style into its lowercased words, like `IconButton`, `icon-button`
+/// or `ICON_BUTTON` into `icon` and `button`
+pub fn words(slice: &str) -> Vec<String> {
+	let mut words = vec![];
+
+	for part in slice.split(|c: char| !c.is_alphanumeric()) {
+		let chars = part.chars().collect::<Vec<_>>();
+		let mut word = String::new();
+
+		for (i, c) in chars.iter().enumerate() {
+			let previous = i.checked_sub(1).map(|i| chars[i]);
+			let next = chars.get(i + 1);
+			// A new word starts at `iconButton` or at the end of an acronym, like `HTTPServer`
+			let after_lowercase = previous.is_some_and(|previous| !previous.is_uppercase());
+			let ends_acronym =
+				previous.is_some_and(char::is_uppercase) && next.is_some_and(|next| next.is_lowercase());
+			let starts_word = c.is_uppercase() && (after_lowercase || ends_acronym);
+
+			if starts_word && !word.is_empty() {
+				words.push(std::mem::take(&mut word));
+			}
+			word.extend(c.to_lowercase());
+		}
+
+		if !word.is_empty() {
+			words.push(word);
+		}
+	}
+
+	words
+}
+
+/// `icon button` into `IconButton`
+pub fn pascal_case(slice: &str) -> String {
+	words(slice)
+		.iter()
+		.map(|word| capitalize_once(word))
+		.collect()
+}
+
+/// `icon button` into `iconButton`
+pub fn camel_case(slice: &str) -> String {
+	let pascal = pascal_case(slice);
+	let mut chars = pascal.chars();
+	match chars.next() {
+		Some(first) => first.to_lowercase().chain(chars).collect(),
+		None => pascal,
+	}
+}
+
+/// `icon button` into `icon_button`
+pub fn snake_case(slice: &str) -> String {
+	words(slice).join("_")
+}
+
+/// `icon button` into `icon-button`
+pub fn kebab_case(slice: &str) -> String {
+	words(slice).join("-")
+}
+
+/// `icon button` into `ICON_BUTTON`
+pub fn constant_case(slice: &str) -> String {
+	snake_case(slice).to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_convert_between_case_styles() {
+		assert_eq!(words("HTTPServer2Go"), vec!["http", "server2", "go"]);
+		assert_eq!(pascal_case("icon-button"), "IconButton");
+		assert_eq!(camel_case("IconButton"), "iconButton");
+		assert_eq!(snake_case("iconButton"), "icon_button");
+		assert_eq!(kebab_case("ICON_BUTTON"), "icon-button");
+		assert_eq!(constant_case("icon button"), "ICON_BUTTON");
+	}
+}